@@ -0,0 +1,97 @@
+//! Ticks matches with thousands of balls bouncing between the walls, paddles and goals, and
+//! fails if the time per ball grows with the number of balls. Scored balls wait for their
+//! launch spread out around the middle, so they cost about the same as balls in flight.
+//!
+//! Run with `cargo bench --bench collisions`.
+use bevy::{
+    ecs::event::{Events, ManualEventReader},
+    prelude::*,
+};
+use bevy_pong::{config::PongConfig, game_entities::*, headless, trajectory::ArenaBounds};
+use std::time::{Duration, Instant};
+
+const BALL_COUNTS: [u32; 3] = [1000, 2000, 4000];
+const WARMUP_TICKS: u32 = 30;
+const MEASURED_TICKS: u32 = 240;
+const BALL_SPEED: f32 = 600.0;
+/// How much more a ball may cost in the largest match than in the smallest, before the cost
+/// counts as growing with the number of balls.
+const MAX_COST_GROWTH: f64 = 1.5;
+
+/// A match with `balls` extra balls spread over the arena on a grid, all moving already.
+fn stress_app(balls: u32) -> App {
+    let mut config = PongConfig::default();
+    config.ball.radius = 4.0;
+    let mut app = headless::headless_app();
+    // thousands of balls score far more than a match lasts
+    app.insert_resource(config).insert_resource(WinCondition {
+        target_score: i32::MAX,
+        win_by: 1,
+    });
+    headless::run_ticks(&mut app, 1);
+
+    let bounds = *app.world.resource::<ArenaBounds>();
+    let size = bounds.max - bounds.min;
+    let columns = (balls as f32 * size.x / size.y).sqrt().ceil() as u32;
+    let rows = balls.div_ceil(columns);
+    let spacing = size / Vec2::new(columns as f32, rows as f32);
+    let mut spawn_ev = app.world.resource_mut::<Events<SpawnBall>>();
+    for i in 0..balls {
+        let cell = Vec2::new((i % columns) as f32, (i / columns) as f32) + 0.5;
+        // golden angle steps, so neighbours head in very different directions
+        let angle = i as f32 * 2.399_963;
+        spawn_ev.send(SpawnBall {
+            position: bounds.min + cell * spacing,
+            velocity: Some(Vec2::new(angle.cos(), angle.sin()) * BALL_SPEED),
+        });
+    }
+    app
+}
+
+fn main() {
+    let mut costs = Vec::new();
+    for balls in BALL_COUNTS {
+        let mut app = stress_app(balls);
+        headless::run_ticks(&mut app, WARMUP_TICKS);
+
+        let mut paddle_hits = ManualEventReader::<BallHitPaddle>::default();
+        let mut wall_hits = ManualEventReader::<BallHitWall>::default();
+        let mut goals = ManualEventReader::<BallEnteredGoal>::default();
+        let mut collisions = 0;
+        let mut waiting = 0;
+        let mut elapsed = Duration::ZERO;
+        for _ in 0..MEASURED_TICKS {
+            let start = Instant::now();
+            app.update();
+            elapsed += start.elapsed();
+
+            let world = &app.world;
+            collisions += paddle_hits.iter(world.resource()).count()
+                + wall_hits.iter(world.resource()).count()
+                + goals.iter(world.resource()).count();
+            let mut timers = app.world.query::<&BallLaunchTimer>();
+            waiting += timers.iter(&app.world).filter(|x| !x.0.finished()).count();
+        }
+        let per_tick = elapsed / MEASURED_TICKS;
+        let per_ball = per_tick.as_secs_f64() / balls as f64;
+        costs.push(per_ball);
+
+        println!(
+            "{:>5} balls: {:>8.3} ms/tick, {:>6.3} us/ball/tick, {:>6} collisions, {:>6.1} waiting",
+            balls,
+            per_tick.as_secs_f64() * 1e3,
+            per_ball * 1e6,
+            collisions,
+            waiting as f64 / MEASURED_TICKS as f64
+        );
+    }
+
+    let growth = costs[costs.len() - 1] / costs[0];
+    assert!(
+        growth <= MAX_COST_GROWTH,
+        "time per ball grew {:.2}x from {} to {} balls",
+        growth,
+        BALL_COUNTS[0],
+        BALL_COUNTS[BALL_COUNTS.len() - 1]
+    );
+}
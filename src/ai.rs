@@ -0,0 +1,166 @@
+use crate::{
+    rng::GameRng,
+    trajectory::{predict_intercept, ArenaBounds},
+    utils::approx_eq,
+};
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::Velocity;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// Snapshot of everything an `AiController` may look at when deciding how to move.
+pub struct AiContext {
+    pub paddle_position: Vec2,
+    pub paddle_velocity: f32,
+    /// Speed the paddle loses per second once it's let go.
+    pub paddle_deceleration: f32,
+    pub ball_position: Vec2,
+    pub ball_velocity: Vec2,
+    pub ball_radius: f32,
+    pub arena: ArenaBounds,
+    pub elapsed: f32,
+    pub time_since_velocity_change: f32,
+}
+
+impl AiContext {
+    fn is_ball_approaching(&self) -> bool {
+        let to_paddle = self.paddle_position.x - self.ball_position.x;
+        to_paddle * self.ball_velocity.x > 0.0
+    }
+}
+
+pub trait AiController: Send + Sync {
+    /// Returns the desired vertical input, in the same [-1, 1] range as a player's axis.
+    fn decide(&mut self, ctx: &AiContext, rng: &mut GameRng) -> f32;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum AiDifficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+}
+
+impl AiDifficulty {
+    pub fn controller(&self) -> Box<dyn AiController> {
+        match self {
+            AiDifficulty::Easy => Box::new(HumanLike {
+                reaction_delay: 0.35,
+                max_error: 80.0,
+                predict: false,
+                ..default()
+            }),
+            AiDifficulty::Normal => Box::new(HumanLike {
+                reaction_delay: 0.15,
+                max_error: 30.0,
+                predict: true,
+                ..default()
+            }),
+            AiDifficulty::Hard => Box::new(TrajectoryPredictor { deadzone: 10.0 }),
+        }
+    }
+}
+
+/// Follows the ball's current height, ignoring where it is heading.
+pub struct ReactiveChaser {
+    pub deadzone: f32,
+}
+
+impl Default for ReactiveChaser {
+    fn default() -> Self {
+        ReactiveChaser { deadzone: 45.0 }
+    }
+}
+
+impl AiController for ReactiveChaser {
+    fn decide(&mut self, ctx: &AiContext, _rng: &mut GameRng) -> f32 {
+        steer(ctx, ctx.ball_position.y, self.deadzone)
+    }
+}
+
+/// Moves to where the ball will cross the paddle, accounting for wall bounces,
+/// and drifts back to the center while the ball moves away.
+pub struct TrajectoryPredictor {
+    pub deadzone: f32,
+}
+
+impl AiController for TrajectoryPredictor {
+    fn decide(&mut self, ctx: &AiContext, _rng: &mut GameRng) -> f32 {
+        let target = if ctx.is_ball_approaching() {
+            predict_intercept_y(ctx)
+        } else {
+            0.0
+        };
+        steer(ctx, target, self.deadzone)
+    }
+}
+
+/// Re-evaluates its target only every `reaction_delay` seconds, aims with up to `max_error`
+/// units of error and cannot reverse direction faster than its reaction time allows.
+pub struct HumanLike {
+    pub reaction_delay: f32,
+    pub max_error: f32,
+    pub predict: bool,
+    pub deadzone: f32,
+    last_think_time: f32,
+    target_y: f32,
+}
+
+impl Default for HumanLike {
+    fn default() -> Self {
+        HumanLike {
+            reaction_delay: 0.2,
+            max_error: 40.0,
+            predict: false,
+            deadzone: 30.0,
+            last_think_time: f32::NEG_INFINITY,
+            target_y: 0.0,
+        }
+    }
+}
+
+impl AiController for HumanLike {
+    fn decide(&mut self, ctx: &AiContext, rng: &mut GameRng) -> f32 {
+        if ctx.elapsed - self.last_think_time >= self.reaction_delay {
+            self.last_think_time = ctx.elapsed;
+            let target = if self.predict && ctx.is_ball_approaching() {
+                predict_intercept_y(ctx)
+            } else {
+                ctx.ball_position.y
+            };
+            self.target_y = target + (rng.gen::<f32>() * 2.0 - 1.0) * self.max_error;
+        }
+
+        let input = steer(ctx, self.target_y, self.deadzone);
+        let reversing = input * ctx.paddle_velocity < 0.0;
+        if reversing && ctx.time_since_velocity_change < self.reaction_delay {
+            ctx.paddle_velocity.signum()
+        } else {
+            input
+        }
+    }
+}
+
+/// Heads the paddle for `to`, letting go early enough for it to slow down there.
+fn steer(ctx: &AiContext, to: f32, deadzone: f32) -> f32 {
+    let diff = to - ctx.paddle_position.y;
+    let stopping_distance = ctx.paddle_velocity.powi(2) / (2.0 * ctx.paddle_deceleration);
+    let arriving = diff * ctx.paddle_velocity > 0.0 && stopping_distance >= diff.abs();
+    if approx_eq(diff, 0.0, deadzone) || arriving {
+        0.0
+    } else {
+        diff.signum()
+    }
+}
+
+fn predict_intercept_y(ctx: &AiContext) -> f32 {
+    predict_intercept(
+        ctx.ball_position,
+        &Velocity::linear(ctx.ball_velocity),
+        ctx.ball_radius,
+        &ctx.arena,
+        ctx.paddle_position.x,
+    )
+    .map_or(ctx.ball_position.y, |x| x.point.y)
+}
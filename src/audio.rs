@@ -0,0 +1,125 @@
+use super::{config::PongConfig, game_entities::*};
+use bevy::{prelude::*, utils::HashSet};
+use iyes_loopless::prelude::*;
+
+/// Toggles `AudioMuted` at any time.
+pub const MUTE_KEY: KeyCode = KeyCode::F1;
+
+/// Silences every sound while set, whatever the configured volume.
+#[derive(Default)]
+pub struct AudioMuted(pub bool);
+
+/// A ball started waiting for its launch.
+pub struct CountdownStarted {
+    pub ball: Entity,
+}
+
+/// Sounds loaded from the paths in `AudioConfig`.
+#[derive(Default)]
+pub struct SoundHandles {
+    pub paddle_hit: Option<Handle<AudioSource>>,
+    pub wall_bounce: Option<Handle<AudioSource>>,
+    pub goal: Option<Handle<AudioSource>>,
+    pub countdown: Option<Handle<AudioSource>>,
+    pub match_won: Option<Handle<AudioSource>>,
+}
+
+/// Plays the configured sounds on gameplay events. Needs bevy's `AudioPlugin`, so it's left
+/// out of headless apps.
+pub struct PongAudio;
+impl Plugin for PongAudio {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<AudioMuted>()
+            .init_resource::<SoundHandles>()
+            .add_event::<CountdownStarted>()
+            .add_system(load_sounds)
+            .add_system(toggle_mute)
+            // after the tick that sent the events, in the same frame
+            .add_system_to_stage(CoreStage::PostUpdate, detect_countdowns.before(play_sounds))
+            .add_system_to_stage(CoreStage::PostUpdate, play_sounds);
+    }
+}
+
+/// Reloads the sounds whenever the config changes.
+pub fn load_sounds(
+    config: Res<PongConfig>,
+    asset_server: Res<AssetServer>,
+    mut sounds: ResMut<SoundHandles>,
+) {
+    if !config.is_changed() {
+        return;
+    }
+
+    let load = |path: &Option<String>| path.as_ref().map(|x| asset_server.load(x.as_str()));
+    let audio = &config.audio;
+    *sounds = SoundHandles {
+        paddle_hit: load(&audio.paddle_hit),
+        wall_bounce: load(&audio.wall_bounce),
+        goal: load(&audio.goal),
+        countdown: load(&audio.countdown),
+        match_won: load(&audio.match_won),
+    };
+}
+
+pub fn toggle_mute(keyboard_input: Res<Input<KeyCode>>, mut muted: ResMut<AudioMuted>) {
+    if keyboard_input.just_pressed(MUTE_KEY) {
+        muted.0 = !muted.0;
+    }
+}
+
+/// Tells when a ball starts waiting for its launch, however it came to: at the start of a
+/// match, after a goal or spawned mid-match.
+pub fn detect_countdowns(
+    state: Res<CurrentState<GameState>>,
+    mut waiting: Local<HashSet<Entity>>,
+    query: Query<(Entity, &BallLaunchTimer)>,
+    mut countdown_ev: EventWriter<CountdownStarted>,
+) {
+    // launch timers only run while playing
+    if state.0 != GameState::Playing {
+        return;
+    }
+
+    let now: HashSet<Entity> = query
+        .iter()
+        .filter(|(_, timer)| !timer.0.finished())
+        .map(|(ball, _)| ball)
+        .collect();
+    for ball in now.difference(&waiting) {
+        countdown_ev.send(CountdownStarted { ball: *ball });
+    }
+    *waiting = now;
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn play_sounds(
+    audio: Res<Audio>,
+    config: Res<PongConfig>,
+    muted: Res<AudioMuted>,
+    sounds: Res<SoundHandles>,
+    mut paddle_hit_ev: EventReader<BallHitPaddle>,
+    mut wall_hit_ev: EventReader<BallHitWall>,
+    mut goal_ev: EventReader<GoalEvent>,
+    mut countdown_ev: EventReader<CountdownStarted>,
+    mut won_ev: EventReader<MatchWon>,
+) {
+    // drained either way, so unmuting doesn't play a backlog
+    let cues = [
+        (&sounds.paddle_hit, paddle_hit_ev.iter().count()),
+        (&sounds.wall_bounce, wall_hit_ev.iter().count()),
+        (&sounds.goal, goal_ev.iter().count()),
+        (&sounds.countdown, countdown_ev.iter().count()),
+        (&sounds.match_won, won_ev.iter().count()),
+    ];
+    if muted.0 || config.audio.volume <= 0.0 {
+        return;
+    }
+
+    let settings = PlaybackSettings::ONCE.with_volume(config.audio.volume);
+    for (sound, count) in cues {
+        // the same sound several times in one frame only makes for a louder one
+        if let (Some(sound), 1..) = (sound, count) {
+            audio.play_with_settings(sound.clone(), settings.clone());
+        }
+    }
+}
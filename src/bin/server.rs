@@ -0,0 +1,34 @@
+use bevy::{app::ScheduleRunnerSettings, log::LogPlugin};
+use bevy_pong::{headless, rng::GameRng, server::GameServer};
+use std::{env, process, time::Duration};
+
+const DEFAULT_ADDR: &str = "127.0.0.1:7878";
+
+/// Runs a match without a window for players and spectators connecting over TCP.
+fn main() {
+    let addr = match env::args().skip(1).collect::<Vec<_>>().as_slice() {
+        [] => DEFAULT_ADDR.to_string(),
+        [addr] => addr.clone(),
+        _ => {
+            eprintln!("usage: server [bind addr, default {}]", DEFAULT_ADDR);
+            process::exit(2);
+        }
+    };
+    let server = match GameServer::bind(&addr) {
+        Ok(server) => server,
+        Err(e) => {
+            eprintln!("could not listen on {}: {}", addr, e);
+            process::exit(1);
+        }
+    };
+
+    let mut app = headless::headless_app();
+    // one update per tick, in real time
+    app.insert_resource(ScheduleRunnerSettings::run_loop(Duration::from_secs_f64(
+        1.0 / headless::TICK_RATE,
+    )))
+    .insert_resource(GameRng::new(rand::random()))
+    .insert_resource(server)
+    .add_plugin(LogPlugin)
+    .run();
+}
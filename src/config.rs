@@ -0,0 +1,392 @@
+use super::game_entities::{Arena, Ball, Paddle};
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::TypeUuid,
+    utils::BoxedFuture,
+};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Asset path, relative to the assets folder, of the config loaded at startup.
+pub const DEFAULT_CONFIG_PATH: &str = "pong.config.ron";
+
+/// Where the game config is loaded from. `path: None` keeps the built-in defaults and never
+/// touches the asset server.
+pub struct PongConfigSource {
+    pub path: Option<String>,
+}
+
+impl Default for PongConfigSource {
+    fn default() -> Self {
+        PongConfigSource {
+            path: Some(DEFAULT_CONFIG_PATH.to_string()),
+        }
+    }
+}
+
+/// Handle to the config asset, kept alive so edits to the file are picked up.
+pub struct PongConfigHandle(pub Handle<PongConfig>);
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Parse(ron::Error),
+    InvalidValue {
+        field: &'static str,
+        value: f32,
+        expected: &'static str,
+    },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Parse(source) => write!(f, "invalid game config: {}", source),
+            ConfigError::InvalidValue {
+                field,
+                value,
+                expected,
+            } => write!(
+                f,
+                "{} is {} but must be {} in the game config",
+                field, value, expected
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ConfigError::Parse(source) => Some(source),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BallConfig {
+    pub radius: f32,
+    pub initial_speed: f32,
+    /// Applied to the ball's velocity on every paddle hit.
+    pub speed_multiplier: f32,
+    pub max_speed: f32,
+    /// Launched balls never move slower than this along the x axis.
+    pub min_horizontal_speed: f32,
+    /// Seconds a ball waits before it's launched, at the start and after every goal.
+    pub launch_delay: f32,
+    /// Radians a spinning ball's flight turns per second, per radian per second of spin.
+    pub magnus: f32,
+    /// Fraction of its spin a ball loses every second.
+    pub spin_decay: f32,
+}
+
+impl Default for BallConfig {
+    fn default() -> Self {
+        BallConfig {
+            radius: 12.5,
+            initial_speed: 500.0,
+            speed_multiplier: 1.25,
+            max_speed: 2000.0,
+            min_horizontal_speed: 100.0,
+            launch_delay: 0.5,
+            magnus: 0.015,
+            spin_decay: 0.6,
+        }
+    }
+}
+
+impl From<&BallConfig> for Ball {
+    fn from(config: &BallConfig) -> Self {
+        Ball {
+            initial_speed: config.initial_speed,
+            speed_multiplier: config.speed_multiplier,
+            max_speed: config.max_speed,
+            min_horizontal_speed: config.min_horizontal_speed,
+            magnus: config.magnus,
+            spin_decay: config.spin_decay,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PaddleConfig {
+    pub size: Vec2,
+    pub speed: f32,
+    /// Speed gained per second while speeding up.
+    pub acceleration: f32,
+    /// Speed lost per second while slowing down or turning around.
+    pub deceleration: f32,
+    /// Speed of the dash started by pressing the same direction twice in quick succession.
+    /// Zero turns dashing off.
+    pub dash_speed: f32,
+    pub dash_duration: f32,
+    /// Seconds from the start of a dash until the next one.
+    pub dash_cooldown: f32,
+    /// Angle in degrees the ball leaves at when it hits the very edge of a paddle.
+    pub max_bounce_angle: f32,
+    /// Up to this many degrees are added to every angled bounce.
+    pub bounce_noise: f32,
+    /// Share of the paddle's vertical velocity added to the ball it hits.
+    pub english: f32,
+    /// Share of the paddle's surface speed the ball picks up as spin.
+    pub spin: f32,
+}
+
+impl Default for PaddleConfig {
+    fn default() -> Self {
+        PaddleConfig {
+            size: Vec2::new(50.0, 250.0),
+            speed: 500.0,
+            acceleration: 5000.0,
+            deceleration: 8000.0,
+            dash_speed: 0.0,
+            dash_duration: 0.15,
+            dash_cooldown: 1.0,
+            max_bounce_angle: 45.0,
+            bounce_noise: 1.0,
+            english: 0.4,
+            spin: 0.5,
+        }
+    }
+}
+
+impl From<&PaddleConfig> for Paddle {
+    fn from(config: &PaddleConfig) -> Self {
+        Paddle {
+            speed: config.speed,
+            acceleration: config.acceleration,
+            deceleration: config.deceleration,
+            dash_speed: config.dash_speed,
+            dash_duration: config.dash_duration,
+            dash_cooldown: config.dash_cooldown,
+        }
+    }
+}
+
+/// Sounds played on gameplay events, as asset paths relative to the assets folder. Events
+/// without a sound stay silent. None ship with the game, so all of them are silent unless
+/// pointed at sounds of your own, e.g. `Some("sounds/goal.ogg")`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AudioConfig {
+    /// Applied to every sound, from 0 for silent to 1 for full volume.
+    pub volume: f32,
+    pub paddle_hit: Option<String>,
+    pub wall_bounce: Option<String>,
+    pub goal: Option<String>,
+    /// Played as a ball starts waiting for its launch.
+    pub countdown: Option<String>,
+    pub match_won: Option<String>,
+}
+
+impl Default for AudioConfig {
+    fn default() -> Self {
+        AudioConfig {
+            volume: 1.0,
+            paddle_hit: None,
+            wall_bounce: None,
+            goal: None,
+            countdown: None,
+            match_won: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize, TypeUuid)]
+#[uuid = "6a4bb0a6-3b8e-4f0e-9d8a-2f5c7f1e4c21"]
+#[serde(default)]
+pub struct PongConfig {
+    pub arena: Arena,
+    pub ball: BallConfig,
+    pub paddle: PaddleConfig,
+    pub audio: AudioConfig,
+}
+
+impl PongConfig {
+    pub fn from_ron(contents: &[u8]) -> Result<Self, ConfigError> {
+        let config: PongConfig = ron::de::from_bytes(contents).map_err(ConfigError::Parse)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let positive = [
+            ("arena.width", self.arena.width),
+            ("arena.height", self.arena.height),
+            ("arena.wall_thickness", self.arena.wall_thickness),
+            ("arena.goal_height", self.arena.goal_height),
+            ("ball.radius", self.ball.radius),
+            ("ball.initial_speed", self.ball.initial_speed),
+            ("ball.speed_multiplier", self.ball.speed_multiplier),
+            ("paddle.size.x", self.paddle.size.x),
+            ("paddle.size.y", self.paddle.size.y),
+            ("paddle.speed", self.paddle.speed),
+            ("paddle.acceleration", self.paddle.acceleration),
+            ("paddle.deceleration", self.paddle.deceleration),
+        ];
+        for (field, value) in positive {
+            check(field, value, "positive", value > 0.0)?;
+        }
+
+        check(
+            "ball.max_speed",
+            self.ball.max_speed,
+            "at least ball.initial_speed",
+            self.ball.max_speed >= self.ball.initial_speed,
+        )?;
+        check(
+            "ball.min_horizontal_speed",
+            self.ball.min_horizontal_speed,
+            "between zero and ball.initial_speed",
+            (0.0..=self.ball.initial_speed).contains(&self.ball.min_horizontal_speed),
+        )?;
+        check(
+            "ball.launch_delay",
+            self.ball.launch_delay,
+            "zero or more",
+            self.ball.launch_delay >= 0.0,
+        )?;
+        check(
+            "paddle.max_bounce_angle",
+            self.paddle.max_bounce_angle,
+            "in [0, 90)",
+            (0.0..90.0).contains(&self.paddle.max_bounce_angle),
+        )?;
+        check(
+            "paddle.bounce_noise",
+            self.paddle.bounce_noise,
+            "zero or more",
+            self.paddle.bounce_noise >= 0.0,
+        )?;
+        let non_negative = [
+            ("ball.magnus", self.ball.magnus),
+            ("paddle.english", self.paddle.english),
+            ("paddle.spin", self.paddle.spin),
+            ("paddle.dash_speed", self.paddle.dash_speed),
+            ("paddle.dash_duration", self.paddle.dash_duration),
+            ("paddle.dash_cooldown", self.paddle.dash_cooldown),
+        ];
+        for (field, value) in non_negative {
+            check(field, value, "zero or more", value >= 0.0)?;
+        }
+        check(
+            "ball.spin_decay",
+            self.ball.spin_decay,
+            "in [0, 1]",
+            (0.0..=1.0).contains(&self.ball.spin_decay),
+        )?;
+        check(
+            "audio.volume",
+            self.audio.volume,
+            "in [0, 1]",
+            (0.0..=1.0).contains(&self.audio.volume),
+        )?;
+        check(
+            "paddle.size.y",
+            self.paddle.size.y,
+            "smaller than the arena's height",
+            self.paddle.size.y < self.arena.height - self.arena.wall_thickness,
+        )?;
+        check(
+            "arena.paddle_inset",
+            self.arena.paddle_inset,
+            "between the goal line and the center",
+            self.arena.paddle_inset > 0.0 && self.arena.paddle_inset < self.arena.width / 2.0,
+        )?;
+        Ok(())
+    }
+}
+
+fn check(
+    field: &'static str,
+    value: f32,
+    expected: &'static str,
+    ok: bool,
+) -> Result<(), ConfigError> {
+    if ok {
+        Ok(())
+    } else {
+        Err(ConfigError::InvalidValue {
+            field,
+            value,
+            expected,
+        })
+    }
+}
+
+/// Loads `*.config.ron` files. Configs that fail validation are rejected, so a bad edit
+/// while the game is running keeps the previous config in place.
+#[derive(Default)]
+pub struct PongConfigLoader;
+
+impl AssetLoader for PongConfigLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let config = PongConfig::from_ron(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(config));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["config.ron"]
+    }
+}
+
+pub struct PongConfigPlugin;
+impl Plugin for PongConfigPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PongConfigSource>()
+            .init_resource::<PongConfig>()
+            .add_asset::<PongConfig>()
+            .init_asset_loader::<PongConfigLoader>()
+            .add_startup_system(load_config)
+            .add_system(update_config);
+    }
+}
+
+pub fn load_config(
+    mut commands: Commands,
+    source: Res<PongConfigSource>,
+    asset_server: Res<AssetServer>,
+) {
+    if let Some(path) = &source.path {
+        commands.insert_resource(PongConfigHandle(asset_server.load(path.as_str())));
+    }
+}
+
+/// Copies the config asset into the `PongConfig` resource whenever it's loaded or changed.
+pub fn update_config(
+    mut asset_ev: EventReader<AssetEvent<PongConfig>>,
+    assets: Res<Assets<PongConfig>>,
+    handle: Option<Res<PongConfigHandle>>,
+    mut config: ResMut<PongConfig>,
+) {
+    let handle = match handle {
+        Some(handle) => handle,
+        None => return,
+    };
+
+    for ev in asset_ev.iter() {
+        match ev {
+            AssetEvent::Created { handle: changed } | AssetEvent::Modified { handle: changed }
+                if *changed == handle.0 =>
+            {
+                if let Some(loaded) = assets.get(changed) {
+                    if *config != *loaded {
+                        info!("game config updated");
+                        *config = loaded.clone();
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+}
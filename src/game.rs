@@ -1,34 +1,133 @@
 use super::{
-    game_entities::*, game_setup_systems::*, game_systems::*, game_ui_setup_systems::*,
-    game_ui_systems::*, input,
+    ai::AiDifficulty,
+    config::PongConfigPlugin,
+    game_entities::*,
+    game_setup_systems::*,
+    game_systems::*,
+    game_ui_setup_systems::*,
+    game_ui_systems::*,
+    net::{NetSession, PongNet},
+    powerups::PongPowerUps,
+    replay::PongReplay,
+    rng::GameRng,
+    server::PongServer,
+    tick::{self, FixedTick, FixedTickStage, TickAppExt, TickStage},
 };
 use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
 use iyes_loopless::prelude::*;
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, SystemLabel)]
 pub enum Label {
     Setup,
+    Clock,
+    Replay,
+    NetInput,
     CollisionCheck,
     BallLaunch,
+    PowerUps,
     Default,
     UI,
+    // systems inside a `ConditionSet` lose their function labels, so ordering between them
+    // needs these
+    CollisionEvents,
+    Score,
+    AiTargets,
+    PaddleMovement,
+    PowerUpCollection,
+    BallStart,
+    BallReset,
+    BallCurve,
+    BallSpeedLimit,
+    AiBrains,
 }
 
+pub const PIXELS_PER_METER: f32 = 100.0;
+
+/// Gameplay and physics run in the `FixedTick` stage, at the rate and in the mode of the
+/// `GameClock` resource, so the app must add `RapierPhysicsPlugin` with
+/// `with_default_system_setup(false)`.
 pub struct PongGame;
 impl Plugin for PongGame {
     fn build(&self, app: &mut App) {
+        let clock = app
+            .world
+            .get_resource::<GameClock>()
+            .cloned()
+            .unwrap_or_default();
+        let mut tick_stage = FixedTickStage::new(clock.mode, clock.step);
+        for (stage, physics) in [
+            (TickStage::PhysicsSync, PhysicsStages::SyncBackend),
+            (TickStage::PhysicsStep, PhysicsStages::StepSimulation),
+            (TickStage::PhysicsWriteback, PhysicsStages::Writeback),
+        ] {
+            tick_stage
+                .stage_mut(stage)
+                .add_system_set(RapierPhysicsPlugin::<NoUserData>::get_systems(physics));
+        }
+        app.add_stage_after(CoreStage::Update, FixedTick, tick_stage)
+            .add_stage_before(
+                CoreStage::Last,
+                PhysicsStages::DetectDespawn,
+                SystemStage::parallel().with_system_set(
+                    RapierPhysicsPlugin::<NoUserData>::get_systems(PhysicsStages::DetectDespawn),
+                ),
+            );
+        if clock.mode == TickMode::RealTime {
+            tick::add_interpolation_systems(app);
+        }
+
         app.insert_resource(ClearColor(Color::BLACK))
-            .insert_resource(BallLaunchDelay(Timer::from_seconds(0.5, false)))
+            .insert_resource(clock)
+            .init_resource::<Arena>()
+            .init_resource::<GameRng>()
+            .init_resource::<WinCondition>()
+            .init_resource::<MatchMode>()
+            .init_resource::<AiDifficulty>()
+            .init_resource::<BallCount>()
+            .init_resource::<StartedContacts>()
             .add_event::<BallLaunch>()
+            .add_event::<SpawnBall>()
             .add_event::<GoalEvent>()
+            .add_event::<MatchWon>()
+            .add_event::<BallHitPaddle>()
+            .add_event::<BallHitWall>()
+            .add_event::<BallEnteredGoal>()
+            .add_loopless_state(GameState::MainMenu)
+            .add_plugin(PongConfigPlugin)
+            .add_plugin(PongPowerUps)
+            .add_plugin(PongReplay)
+            .add_plugin(PongNet)
+            .add_plugin(PongServer)
+            .add_enter_system(GameState::MainMenu, spawn_main_menu)
+            .add_enter_system(GameState::MainMenu, reset_match)
+            .add_exit_system(GameState::MainMenu, despawn_menu)
+            .add_exit_system(GameState::MainMenu, apply_match_mode)
+            .add_enter_system(GameState::Playing, set_physics_active(true))
+            .add_exit_system(GameState::Playing, set_physics_active(false))
+            .add_enter_system(GameState::Paused, spawn_pause_menu)
+            .add_exit_system(GameState::Paused, despawn_menu)
+            .add_enter_system(GameState::GameOver, freeze_match)
+            .add_enter_system(GameState::GameOver, spawn_game_over_menu)
+            .add_exit_system(GameState::GameOver, despawn_menu)
+            .add_exit_system(GameState::GameOver, reset_match)
+            // paused or between matches, gameplay time stands still
+            .add_tick_system(
+                TickStage::Gameplay,
+                advance_game_clock
+                    .run_in_state(GameState::Playing)
+                    .label(Label::Clock),
+            )
             .add_startup_system_set(
                 SystemSet::new()
                     .label(Label::Setup)
                     .with_system(setup_cameras)
                     .with_system(setup_physics)
-                    .with_system(spawn_ball)
-                    .with_system(spawn_paddles)
+                    // colliders enter the physics world in spawn order, which decides how
+                    // contacts are solved, so it mustn't change from one run to the next
                     .with_system(spawn_bounds)
+                    .with_system(spawn_paddles.after(spawn_bounds))
+                    .with_system(spawn_balls.after(spawn_paddles))
                     .with_system(spawn_score)
                     .with_system(spawn_hud),
             )
@@ -38,34 +137,143 @@ impl Plugin for PongGame {
             )
             .add_system_set(
                 SystemSet::new()
+                    .with_system(apply_config)
+                    .with_system(layout_arena.after(apply_config))
+                    .with_system(fit_camera_to_window),
+            )
+            .add_tick_system_set(
+                TickStage::Gameplay,
+                ConditionSet::new()
+                    .run_in_state(GameState::Playing)
                     .label(Label::CollisionCheck)
-                    .after(input::Label::Default)
-                    .with_system(evaluate_ball_collision),
+                    .after(Label::Clock)
+                    .with_system(
+                        detect_ball_collisions
+                            .into_conditional()
+                            .label(Label::CollisionEvents),
+                    )
+                    .with_system(
+                        bounce_off_paddles
+                            .into_conditional()
+                            .after(Label::CollisionEvents),
+                    )
+                    .with_system(enter_goals.into_conditional().after(Label::CollisionEvents))
+                    .into(),
             )
-            .add_system_set(
-                SystemSet::new()
+            .add_tick_system_set(
+                TickStage::PostTick,
+                ConditionSet::new()
+                    .run_in_state(GameState::Playing)
+                    .with_system(collect_started_contacts)
+                    .into(),
+            )
+            .add_tick_system_set(
+                TickStage::Gameplay,
+                ConditionSet::new()
+                    .run_in_state(GameState::Playing)
                     .label(Label::BallLaunch)
                     .after(Label::CollisionCheck)
                     .with_system(ball_launch_timer)
-                    .with_system(update_ball_launch_timer),
+                    .into(),
             )
-            .add_system_set(
-                SystemSet::new()
+            .add_tick_system_set(
+                TickStage::Gameplay,
+                ConditionSet::new()
+                    .run_in_state(GameState::Playing)
                     .label(Label::Default)
                     .after(Label::BallLaunch)
-                    .with_system(start_ball_movement.run_if(is_ball_launch_ready))
-                    .with_system(prevent_stuck_ball.run_if(was_ball_launched))
-                    .with_system(score)
-                    .with_system(reset_ball)
-                    .with_system(paddle_movement)
-                    .with_system(enemy_paddle_movement)
-                    .with_system(limit_ball_velocity),
+                    .with_system(
+                        start_ball_movement
+                            .into_conditional()
+                            .label(Label::BallStart),
+                    )
+                    .with_system(handle_spawn_ball)
+                    .with_system(score.into_conditional().label(Label::Score))
+                    .with_system(
+                        // a netplay match ends on the confirmed score only
+                        check_victory
+                            .run_unless_resource_exists::<NetSession>()
+                            .after(Label::Score),
+                    )
+                    .with_system(
+                        reset_ball
+                            .into_conditional()
+                            .label(Label::BallReset)
+                            .after(Label::BallStart),
+                    )
+                    .with_system(
+                        paddle_movement
+                            .into_conditional()
+                            .label(Label::PaddleMovement),
+                    )
+                    .with_system(update_ai_brains.into_conditional().label(Label::AiBrains))
+                    // the AI sees the balls as they leave this tick, and draws randomness
+                    // after the launches do, so replays and rollbacks pick the same
+                    .with_system(
+                        select_ai_targets
+                            .into_conditional()
+                            .label(Label::AiTargets)
+                            .after(Label::AiBrains)
+                            .after(Label::BallSpeedLimit),
+                    )
+                    .with_system(
+                        enemy_paddle_movement
+                            .into_conditional()
+                            .label(Label::PaddleMovement)
+                            .after(Label::AiTargets),
+                    )
+                    .with_system(
+                        constrain_paddles
+                            .into_conditional()
+                            .after(Label::PaddleMovement),
+                    )
+                    .with_system(
+                        curve_spinning_balls
+                            .into_conditional()
+                            .label(Label::BallCurve)
+                            .after(Label::BallReset),
+                    )
+                    .with_system(
+                        limit_ball_velocity
+                            .into_conditional()
+                            .label(Label::BallSpeedLimit)
+                            .after(Label::BallCurve),
+                    )
+                    .into(),
             )
             .add_system_set(
                 SystemSet::new()
                     .label(Label::UI)
-                    .after(Label::Default)
                     .with_system(update_score_runtime),
+            )
+            .add_system_set(
+                ConditionSet::new()
+                    .run_in_state(GameState::MainMenu)
+                    .label(Label::UI)
+                    .with_system(main_menu_input.run_unless_resource_exists::<NetSession>())
+                    .into(),
+            )
+            .add_system_set(
+                ConditionSet::new()
+                    .run_in_state(GameState::Playing)
+                    .label(Label::UI)
+                    .with_system(pause_input.run_unless_resource_exists::<NetSession>())
+                    .with_system(update_ball_launch_timer)
+                    .into(),
+            )
+            .add_system_set(
+                ConditionSet::new()
+                    .run_in_state(GameState::Paused)
+                    .label(Label::UI)
+                    .with_system(paused_input)
+                    .into(),
+            )
+            .add_system_set(
+                ConditionSet::new()
+                    .run_in_state(GameState::GameOver)
+                    .label(Label::UI)
+                    .with_system(game_over_input)
+                    .into(),
             );
     }
 }
@@ -1,12 +1,56 @@
+use super::{
+    ai::{AiController, AiDifficulty},
+    input::PlayerId,
+    trajectory::ArenaBounds,
+    utils::move_towards,
+};
 use bevy::prelude::*;
 use bevy_rapier2d::prelude::*;
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, time::Duration};
 
 pub struct GoalEvent {
-    pub ball_id: u32,
+    pub ball: Entity,
     pub team: Team,
 }
 
+pub struct MatchWon {
+    pub team: Team,
+}
+
+/// A ball started touching a paddle.
+#[derive(Debug, Clone)]
+pub struct BallHitPaddle {
+    pub ball: Entity,
+    pub paddle: Entity,
+}
+
+/// A ball started touching a wall or goal post.
+#[derive(Debug, Clone)]
+pub struct BallHitWall {
+    pub ball: Entity,
+}
+
+/// A ball started touching a goal, which scores for the other team.
+#[derive(Debug, Clone)]
+pub struct BallEnteredGoal {
+    pub ball: Entity,
+    pub goal: Entity,
+}
+
+/// Pairs of colliders that started touching during the last physics step, kept until the
+/// next tick's gameplay sorts them into ball events.
+#[derive(Clone, Default)]
+pub struct StartedContacts(pub Vec<(Entity, Entity)>);
+
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq, Serialize, Deserialize)]
+pub enum GameState {
+    MainMenu,
+    Playing,
+    Paused,
+    GameOver,
+}
+
 #[derive(Debug, Clone, Hash, Eq, PartialEq)]
 pub enum Team {
     Player = 0,
@@ -30,9 +74,83 @@ pub trait Reset {
     fn reset(&mut self);
 }
 
+/// How a paddle moves, shared by players and the AI. Inputs only pick a target velocity that
+/// the paddle accelerates towards, so no paddle turns around instantly.
 #[derive(Clone, Component)]
 pub struct Paddle {
+    /// Top speed at full input.
     pub speed: f32,
+    /// Speed gained per second while speeding up towards the input.
+    pub acceleration: f32,
+    /// Speed lost per second while slowing down or turning around.
+    pub deceleration: f32,
+    /// Speed of a dash, zero for paddles that can't dash.
+    pub dash_speed: f32,
+    /// Seconds a dash lasts.
+    pub dash_duration: f32,
+    /// Seconds after a dash starts before the next one can.
+    pub dash_cooldown: f32,
+}
+
+/// Inputs at least this far from the center count as pressing a direction, for dashes.
+pub const DASH_INPUT_THRESHOLD: f32 = 0.5;
+/// A paddle dashes when its input presses the same direction twice within this many seconds.
+pub const DASH_TAP_WINDOW: f32 = 0.25;
+
+/// Where a paddle is in its movement between ticks, kept as time left on each phase so
+/// rollbacks restore it exactly.
+#[derive(Clone, Component, Default)]
+pub struct PaddleMotion {
+    /// Direction pressed during the last tick, zero for none.
+    pub pressed: f32,
+    /// Direction of the last press that may still be followed up by a dash.
+    pub tap_direction: f32,
+    pub tap_time_left: f32,
+    pub dash_direction: f32,
+    pub dash_time_left: f32,
+    pub cooldown_left: f32,
+}
+
+impl PaddleMotion {
+    /// Advances the paddle's movement by `dt` seconds of `input`, in [-1, 1], and returns its
+    /// new vertical velocity.
+    pub fn step(&mut self, paddle: &Paddle, velocity: f32, input: f32, dt: f32) -> f32 {
+        self.tap_time_left = (self.tap_time_left - dt).max(0.0);
+        self.dash_time_left = (self.dash_time_left - dt).max(0.0);
+        self.cooldown_left = (self.cooldown_left - dt).max(0.0);
+
+        let pressed = if input.abs() >= DASH_INPUT_THRESHOLD {
+            input.signum()
+        } else {
+            0.0
+        };
+        if pressed != 0.0 && pressed != self.pressed {
+            let can_dash = paddle.dash_speed > 0.0 && self.cooldown_left <= 0.0;
+            if can_dash && self.tap_time_left > 0.0 && self.tap_direction == pressed {
+                self.dash_direction = pressed;
+                self.dash_time_left = paddle.dash_duration;
+                self.cooldown_left = paddle.dash_cooldown;
+                self.tap_time_left = 0.0;
+            } else {
+                self.tap_direction = pressed;
+                self.tap_time_left = DASH_TAP_WINDOW;
+            }
+        }
+        self.pressed = pressed;
+
+        if self.dash_time_left > 0.0 {
+            return self.dash_direction * paddle.dash_speed;
+        }
+        let target = input.clamp(-1.0, 1.0) * paddle.speed;
+        let speeding_up =
+            velocity == 0.0 || (target * velocity > 0.0 && target.abs() > velocity.abs());
+        let rate = if speeding_up {
+            paddle.acceleration
+        } else {
+            paddle.deceleration
+        };
+        move_towards(velocity, target, rate * dt)
+    }
 }
 
 #[derive(Clone, Component, Default)]
@@ -40,63 +158,352 @@ pub struct PlayerPaddle;
 
 #[derive(Clone, Component, Default)]
 pub struct AIPaddle {
-    pub target_ball: u32,
-    pub last_velocity_change_time: f32
+    pub target_ball: Option<Entity>,
+    pub last_velocity_change_time: f32,
+    pub difficulty: AiDifficulty,
+}
+
+/// Controller driving an `AIPaddle`, rebuilt whenever the paddle's difficulty changes.
+#[derive(Component)]
+pub struct AIBrain {
+    pub difficulty: AiDifficulty,
+    pub controller: Box<dyn AiController>,
 }
 
 #[derive(Clone, Component)]
 pub struct Ball {
     pub initial_speed: f32,
     pub speed_multiplier: f32,
-    pub max_speed: Vec2,
+    pub max_speed: f32,
+    pub min_horizontal_speed: f32,
+    pub magnus: f32,
+    pub spin_decay: f32,
+}
+
+impl Ball {
+    /// Caps the speed at `max_speed` in any direction and keeps the ball moving towards a goal
+    /// at `min_horizontal_speed` or more, so it can't end up bouncing between the walls forever.
+    /// A ball with no horizontal velocity is sent to the right.
+    pub fn limit_velocity(&self, linvel: Vec2) -> Vec2 {
+        let mut limited = linvel.clamp_length_max(self.max_speed);
+        if limited.x.abs() < self.min_horizontal_speed {
+            let x_sign = if limited.x < 0.0 { -1.0 } else { 1.0 };
+            limited.x = x_sign * self.min_horizontal_speed;
+            // trade vertical speed for horizontal so the cap still holds
+            let max_y = (self.max_speed.powi(2) - limited.x.powi(2)).max(0.0).sqrt();
+            limited.y = limited.y.clamp(-max_y, max_y);
+        }
+        limited
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Top,
+    Bottom,
+    Left,
+    Right,
+}
+
+#[derive(Clone, Component)]
+pub struct Bounds {
+    pub side: Side,
+}
+
+/// Marks the left and right `Bounds`, which are split into a post above the goal mouth
+/// (`end: Side::Top`) and one below it (`end: Side::Bottom`).
+#[derive(Clone, Component)]
+pub struct GoalPost {
+    pub end: Side,
+}
+
+/// How far goal sensors reach behind the goal line, far enough that no ball can step over
+/// one within a single tick.
+pub const GOAL_DEPTH: f32 = 200.0;
+
+/// Logical size of the playfield in world units. Everything in the scene is laid out
+/// relative to it, and the camera scales it to fit whatever window it's shown in.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Arena {
+    pub width: f32,
+    pub height: f32,
+    pub wall_thickness: f32,
+    /// Distance from a goal line to the center of the paddle defending it.
+    pub paddle_inset: f32,
+    /// Height of the openings in the left and right walls that count as goals. The whole
+    /// side is open at `height` or more.
+    pub goal_height: f32,
+}
+
+impl Default for Arena {
+    fn default() -> Self {
+        Arena {
+            width: 1920.0,
+            height: 1080.0,
+            wall_thickness: 25.0,
+            paddle_inset: 460.0,
+            goal_height: 1080.0,
+        }
+    }
 }
 
+impl Arena {
+    pub fn size(&self) -> Vec2 {
+        Vec2::new(self.width, self.height)
+    }
+
+    pub fn bounds(&self) -> ArenaBounds {
+        let inner_half_extents = self.size() / 2.0 - self.wall_thickness / 2.0;
+        ArenaBounds {
+            min: -inner_half_extents,
+            max: inner_half_extents,
+        }
+    }
+
+    /// Size and center of the wall on `side`; walls are centered on the arena's edges.
+    pub fn wall(&self, side: Side) -> (Vec2, Vec2) {
+        let half_size = self.size() / 2.0;
+        match side {
+            Side::Top => (
+                Vec2::new(self.width, self.wall_thickness),
+                Vec2::new(0.0, half_size.y),
+            ),
+            Side::Bottom => (
+                Vec2::new(self.width, self.wall_thickness),
+                Vec2::new(0.0, -half_size.y),
+            ),
+            Side::Left => (
+                Vec2::new(self.wall_thickness, self.height),
+                Vec2::new(-half_size.x, 0.0),
+            ),
+            Side::Right => (
+                Vec2::new(self.wall_thickness, self.height),
+                Vec2::new(half_size.x, 0.0),
+            ),
+        }
+    }
+
+    /// Height of the goal mouths, at most the distance between the top and bottom walls.
+    pub fn goal_mouth(&self) -> f32 {
+        self.goal_height.min(self.height - self.wall_thickness)
+    }
+
+    /// Size and center of the post between the goal mouth on `side` and the wall at `end`.
+    /// Posts reach into the corners, so they never shrink to nothing.
+    pub fn goal_post(&self, side: Side, end: Side) -> (Vec2, Vec2) {
+        let (wall_size, wall_center) = self.wall(side);
+        let half_height = self.height / 2.0;
+        let half_mouth = self.goal_mouth() / 2.0;
+        let y = (half_height + half_mouth) / 2.0;
+        (
+            Vec2::new(wall_size.x, half_height - half_mouth),
+            Vec2::new(wall_center.x, if end == Side::Bottom { -y } else { y }),
+        )
+    }
+
+    /// Size and center of the goal sensor on `side`, spanning the mouth from the goal line
+    /// outwards.
+    pub fn goal_zone(&self, side: Side) -> (Vec2, Vec2) {
+        let x = self.width / 2.0 - self.wall_thickness / 2.0 + GOAL_DEPTH / 2.0;
+        (
+            Vec2::new(GOAL_DEPTH, self.goal_mouth()),
+            Vec2::new(if side == Side::Left { -x } else { x }, 0.0),
+        )
+    }
+
+    pub fn paddle_x(&self, player: PlayerId) -> f32 {
+        let x = self.width / 2.0 - self.paddle_inset;
+        match player {
+            PlayerId::One => -x,
+            PlayerId::Two => x,
+        }
+    }
+}
+
+/// Marks the camera that renders the arena.
 #[derive(Clone, Component, Default)]
-pub struct Bounds;
+pub struct GameCamera;
 
+/// Sensor behind a goal mouth. Balls entering it score against `team`.
 #[derive(Clone, Component)]
 pub struct Goal {
     pub team: Team,
+    pub side: Side,
 }
 
-#[derive(Bundle)]
-pub struct PaddleBundle {
-    pub paddle: Paddle,
-    #[bundle]
-    pub sprite: SpriteBundle,
-    pub rb: RigidBody,
-    pub collider: Collider,
-    pub coll_events: ActiveEvents,
-    pub locked_axes: LockedAxes,
-}
+pub use bundles::*;
 
-#[derive(Bundle)]
-pub struct BallBundle {
-    pub ball: Ball,
-    #[bundle]
-    pub sprite: SpriteBundle,
-    pub rb: RigidBody,
-    pub collider: Collider,
-    pub coll_events: ActiveEvents,
-    pub ccd: Ccd,
-}
+// `#[derive(Bundle)]` forgets its fields after moving them out, which newer clippy flags
+#[allow(clippy::forget_non_drop)]
+mod bundles {
+    use super::*;
+
+    #[derive(Bundle)]
+    pub struct GoalBundle {
+        pub goal: Goal,
+        pub transform: Transform,
+        pub global_transform: GlobalTransform,
+        pub collider: Collider,
+        pub sensor: Sensor,
+        pub coll_events: ActiveEvents,
+    }
+
+    #[derive(Bundle)]
+    pub struct PaddleBundle {
+        pub paddle: Paddle,
+        pub motion: PaddleMotion,
+        #[bundle]
+        pub sprite: SpriteBundle,
+        pub rb: RigidBody,
+        pub collider: Collider,
+        pub coll_events: ActiveEvents,
+        pub locked_axes: LockedAxes,
+    }
+
+    #[derive(Bundle)]
+    pub struct BallBundle {
+        pub ball: Ball,
+        pub launch_timer: BallLaunchTimer,
+        pub last_hit: LastHitBy,
+        #[bundle]
+        pub sprite: SpriteBundle,
+        pub rb: RigidBody,
+        pub collider: Collider,
+        pub coll_events: ActiveEvents,
+        pub coll_groups: CollisionGroups,
+        pub ccd: Ccd,
+    }
 
-#[derive(Bundle)]
-pub struct BoundsBundle {
-    pub bounds: Bounds,
-    #[bundle]
-    pub sprite: SpriteBundle,
-    pub collider: Collider,
-    pub coll_events: ActiveEvents,
+    #[derive(Bundle)]
+    pub struct BoundsBundle {
+        pub bounds: Bounds,
+        #[bundle]
+        pub sprite: SpriteBundle,
+        pub collider: Collider,
+        pub coll_events: ActiveEvents,
+    }
 }
 
+/// Counts down before the ball it's attached to is launched; a finished timer means the ball
+/// is in play.
+#[derive(Clone, Component)]
+pub struct BallLaunchTimer(pub Timer);
+
+/// Paddle that last touched the ball, the one credited with power-ups the ball collects.
+#[derive(Clone, Component, Default)]
+pub struct LastHitBy(pub Option<Entity>);
+
 #[derive(Clone, Component, Default)]
 pub struct MatchScore {
     pub score: HashMap<Team, i32>,
 }
 
-pub struct BallLaunchDelay(pub Timer);
-pub struct BallLaunch;
+/// Gameplay and physics ticks per second.
+pub const TICK_RATE: f64 = 60.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TickMode {
+    /// Ticks keep pace with wall-clock time, running as many per frame as time has passed.
+    RealTime,
+    /// Every `App::update` runs exactly one tick, which keeps headless runs independent of
+    /// wall-clock time.
+    PerUpdate,
+}
+
+/// Time source for gameplay systems. Gameplay and physics advance in ticks of `step`,
+/// regardless of the frame rate.
+#[derive(Clone)]
+pub struct GameClock {
+    pub mode: TickMode,
+    pub step: Duration,
+    pub delta: Duration,
+    pub elapsed: Duration,
+    pub tick: u64,
+}
+
+impl GameClock {
+    pub fn per_update(step: Duration) -> Self {
+        GameClock {
+            mode: TickMode::PerUpdate,
+            step,
+            ..default()
+        }
+    }
+
+    pub fn delta_seconds(&self) -> f32 {
+        self.delta.as_secs_f32()
+    }
+}
+
+impl Default for GameClock {
+    fn default() -> Self {
+        GameClock {
+            mode: TickMode::RealTime,
+            step: Duration::from_secs_f64(1.0 / TICK_RATE),
+            delta: Duration::ZERO,
+            elapsed: Duration::ZERO,
+            tick: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum MatchMode {
+    #[default]
+    VsAI,
+    TwoPlayer,
+}
+
+/// A team wins once it reaches `target_score` while leading by at least `win_by` points.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WinCondition {
+    pub target_score: i32,
+    pub win_by: i32,
+}
+
+impl WinCondition {
+    pub fn winner(&self, match_score: &MatchScore) -> Option<Team> {
+        [Team::Player, Team::AI].into_iter().find(|team| {
+            let score = match_score.score.get(team).copied().unwrap_or_default();
+            let other = match_score
+                .score
+                .get(&team.opposite())
+                .copied()
+                .unwrap_or_default();
+            score >= self.target_score && score - other >= self.win_by
+        })
+    }
+}
+
+impl Default for WinCondition {
+    fn default() -> Self {
+        WinCondition {
+            target_score: 11,
+            win_by: 2,
+        }
+    }
+}
+
+pub struct BallLaunch {
+    pub ball: Entity,
+}
+
+/// Number of balls put in play at the start of a match.
+pub struct BallCount(pub usize);
+
+impl Default for BallCount {
+    fn default() -> Self {
+        BallCount(1)
+    }
+}
+
+/// Requests a new ball at `position`. With a `velocity` the ball starts moving right away,
+/// otherwise it waits for the usual launch delay.
+pub struct SpawnBall {
+    pub position: Vec2,
+    pub velocity: Option<Vec2>,
+}
 
 #[derive(Component)]
 pub struct ScoreText {
@@ -105,3 +512,6 @@ pub struct ScoreText {
 
 #[derive(Component)]
 pub struct BallLaunchTimerText;
+
+#[derive(Component)]
+pub struct Menu;
@@ -1,14 +1,24 @@
-use super::game_entities::*;
+use super::{
+    config::PongConfig, game_entities::*, input::PlayerId, powerups::ActiveEffects,
+    tick::TickInterpolation,
+};
 use bevy::prelude::*;
 use bevy_rapier2d::prelude::*;
 use std::collections::HashMap;
 
-pub fn setup_physics(mut physics: ResMut<RapierConfiguration>) {
+pub fn setup_physics(clock: Res<GameClock>, mut physics: ResMut<RapierConfiguration>) {
     physics.gravity = Vec2::ZERO;
+    // physics runs inside the fixed tick, one step per tick
+    physics.timestep_mode = TimestepMode::Fixed {
+        dt: clock.step.as_secs_f32(),
+        substeps: 1,
+    };
 }
 
 pub fn setup_cameras(mut commands: Commands) {
-    commands.spawn_bundle(OrthographicCameraBundle::new_2d());
+    commands
+        .spawn_bundle(OrthographicCameraBundle::new_2d())
+        .insert(GameCamera);
     commands.spawn_bundle(UiCameraBundle::default());
 }
 
@@ -18,91 +28,133 @@ pub fn spawn_score(mut commands: Commands) {
     });
 }
 
-pub fn spawn_paddles(mut commands: Commands) {
-    spawn_player_paddle(&mut commands);
-    spawn_enemy_paddle(&mut commands);
+pub fn spawn_paddles(mut commands: Commands, arena: Res<Arena>, config: Res<PongConfig>) {
+    spawn_player_paddle(&mut commands, &arena, &config);
+    spawn_enemy_paddle(&mut commands, &arena, &config);
+}
+
+pub const BALL_COLLISION_GROUP: u32 = 0b10;
+
+pub fn spawn_balls(
+    mut commands: Commands,
+    assets: Res<AssetServer>,
+    config: Res<PongConfig>,
+    count: Res<BallCount>,
+) {
+    for _ in 0..count.0 {
+        spawn_ball(&mut commands, &assets, &config, Vec2::ZERO);
+    }
 }
 
-pub fn spawn_ball(mut commands: Commands, assets: Res<AssetServer>) {
+pub fn spawn_ball(
+    commands: &mut Commands,
+    assets: &AssetServer,
+    config: &PongConfig,
+    position: Vec2,
+) -> Entity {
+    let radius = config.ball.radius;
     commands
         .spawn_bundle(BallBundle {
-            ball: Ball {
-                initial_speed: 500.0,
-                speed_multiplier: 1.25,
-                max_speed: Vec2::new(2000.0, 2000.0),
-            },
+            ball: Ball::from(&config.ball),
+            launch_timer: BallLaunchTimer(Timer::from_seconds(config.ball.launch_delay, false)),
+            last_hit: default(),
             sprite: SpriteBundle {
                 sprite: Sprite {
                     color: Color::WHITE,
-                    custom_size: Option::Some(Vec2::new(25.0, 25.0)),
+                    custom_size: Option::Some(Vec2::splat(radius * 2.0)),
                     ..default()
                 },
                 texture: assets.load("sprites/circle.png"),
+                transform: Transform::from_translation(position.extend(0.0)),
                 ..default()
             },
-            collider: Collider::ball(12.5),
+            collider: Collider::ball(radius),
             rb: RigidBody::Dynamic,
             ccd: Ccd::enabled(),
             coll_events: ActiveEvents::COLLISION_EVENTS,
+            // balls pass through each other
+            coll_groups: CollisionGroups::new(BALL_COLLISION_GROUP, !BALL_COLLISION_GROUP),
         })
         .insert(Velocity::zero())
         .insert(Restitution::coefficient(1.0))
-        .insert(Friction::coefficient(0.0));
+        .insert(Friction::coefficient(0.0))
+        .insert(TickInterpolation::default())
+        .insert(ActiveEffects::default())
+        .id()
 }
 
-pub fn spawn_bounds(window: Res<WindowDescriptor>, mut commands: Commands) {
-    let height = window.height;
-    let width = window.width;
-    let half_height = height / 2.0;
-    let half_width = width / 2.0;
-    let fixed_size = 25.0;
-    spawn_bound(
-        &mut commands,
-        &Vec2::new(width, fixed_size),
-        &Vec3::new(0.0, half_height, 0.0),
-        &Quat::IDENTITY,
-    );
-    spawn_bound(
-        &mut commands,
-        &Vec2::new(width, fixed_size),
-        &Vec3::new(0.0, -half_height, 0.0),
-        &Quat::IDENTITY,
-    );
-    let right_bound = spawn_bound(
-        &mut commands,
-        &Vec2::new(fixed_size, height),
-        &Vec3::new(half_width, 0.0, 0.0),
-        &Quat::IDENTITY,
-    );
-    let left_bound = spawn_bound(
-        &mut commands,
-        &Vec2::new(fixed_size, height),
-        &Vec3::new(-half_width, 0.0, 0.0),
-        &Quat::IDENTITY,
-    );
+/// Spawns a ball that skips the launch delay and starts moving with `velocity`.
+pub fn spawn_launched_ball(
+    commands: &mut Commands,
+    assets: &AssetServer,
+    config: &PongConfig,
+    position: Vec2,
+    velocity: Vec2,
+) -> Entity {
+    let mut launch_timer = Timer::from_seconds(config.ball.launch_delay, false);
+    launch_timer.tick(launch_timer.duration());
+    let entity = spawn_ball(commands, assets, config, position);
     commands
-        .entity(left_bound)
-        .insert(Goal { team: Team::Player });
-    commands.entity(right_bound).insert(Goal { team: Team::AI });
+        .entity(entity)
+        .insert(Velocity::linear(velocity))
+        .insert(BallLaunchTimer(launch_timer));
+    entity
 }
 
-fn spawn_player_paddle(mut commands: &mut Commands) {
-    let entity = spawn_paddle(&mut commands, &Vec3::new(-500.0, 0.0, 0.0));
-    commands.entity(entity).insert(PlayerPaddle);
+pub fn spawn_bounds(arena: Res<Arena>, mut commands: Commands) {
+    commands.insert_resource(arena.bounds());
+    for side in [Side::Top, Side::Bottom] {
+        let (size, position) = arena.wall(side);
+        spawn_bound(&mut commands, side, &size, &position.extend(0.0));
+    }
+    for (side, team) in [(Side::Left, Team::Player), (Side::Right, Team::AI)] {
+        for end in [Side::Top, Side::Bottom] {
+            let (size, position) = arena.goal_post(side, end);
+            let entity = spawn_bound(&mut commands, side, &size, &position.extend(0.0));
+            commands.entity(entity).insert(GoalPost { end });
+        }
+        spawn_goal(&mut commands, &arena, side, team);
+    }
 }
 
-fn spawn_enemy_paddle(mut commands: &mut Commands) {
-    let entity = spawn_paddle(&mut commands, &Vec3::new(500.0, 0.0, 0.0));
-    commands.entity(entity).insert(AIPaddle::default());
+fn spawn_goal(commands: &mut Commands, arena: &Arena, side: Side, team: Team) {
+    let (size, position) = arena.goal_zone(side);
+    commands.spawn_bundle(GoalBundle {
+        goal: Goal { team, side },
+        transform: Transform::from_translation(position.extend(0.0)),
+        global_transform: default(),
+        collider: Collider::cuboid(size.x / 2.0, size.y / 2.0),
+        sensor: Sensor(true),
+        coll_events: ActiveEvents::COLLISION_EVENTS,
+    });
 }
 
-fn spawn_paddle(commands: &mut Commands, translation: &Vec3) -> Entity {
+fn spawn_player_paddle(commands: &mut Commands, arena: &Arena, config: &PongConfig) {
+    let x = arena.paddle_x(PlayerId::One);
+    let entity = spawn_paddle(commands, config, &Vec3::new(x, 0.0, 0.0));
+    commands
+        .entity(entity)
+        .insert(PlayerPaddle)
+        .insert(PlayerId::One);
+}
+
+fn spawn_enemy_paddle(commands: &mut Commands, arena: &Arena, config: &PongConfig) {
+    let x = arena.paddle_x(PlayerId::Two);
+    let entity = spawn_paddle(commands, config, &Vec3::new(x, 0.0, 0.0));
+    commands
+        .entity(entity)
+        .insert(AIPaddle::default())
+        .insert(PlayerId::Two);
+}
+
+fn spawn_paddle(commands: &mut Commands, config: &PongConfig, translation: &Vec3) -> Entity {
+    let size = config.paddle.size;
     commands
         .spawn_bundle(PaddleBundle {
             sprite: SpriteBundle {
                 sprite: Sprite {
                     color: Color::WHITE,
-                    custom_size: Option::Some(Vec2::new(50.0, 250.0)),
+                    custom_size: Option::Some(size),
                     ..default()
                 },
                 transform: Transform {
@@ -111,9 +163,10 @@ fn spawn_paddle(commands: &mut Commands, translation: &Vec3) -> Entity {
                 },
                 ..default()
             },
-            collider: Collider::cuboid(50.0 / 2.0, 250.0 / 2.0),
+            collider: Collider::cuboid(size.x / 2.0, size.y / 2.0),
             rb: RigidBody::KinematicVelocityBased,
-            paddle: Paddle { speed: 500.0 },
+            paddle: Paddle::from(&config.paddle),
+            motion: default(),
             coll_events: ActiveEvents::COLLISION_EVENTS,
             locked_axes: LockedAxes::all(),
         })
@@ -121,15 +174,12 @@ fn spawn_paddle(commands: &mut Commands, translation: &Vec3) -> Entity {
         .insert(Dominance::group(10))
         .insert(Restitution::coefficient(1.0))
         .insert(Friction::coefficient(0.0))
+        .insert(TickInterpolation::default())
+        .insert(ActiveEffects::default())
         .id()
 }
 
-fn spawn_bound(
-    commands: &mut Commands,
-    size: &Vec2,
-    translation: &Vec3,
-    rotation: &Quat,
-) -> Entity {
+fn spawn_bound(commands: &mut Commands, side: Side, size: &Vec2, translation: &Vec3) -> Entity {
     commands
         .spawn_bundle(BoundsBundle {
             sprite: SpriteBundle {
@@ -138,15 +188,11 @@ fn spawn_bound(
                     custom_size: Option::Some(*size),
                     ..default()
                 },
-                transform: Transform {
-                    translation: *translation,
-                    rotation: *rotation,
-                    ..default()
-                },
+                transform: Transform::from_translation(*translation),
                 ..default()
             },
             collider: Collider::cuboid(size.x / 2.0, size.y / 2.0),
-            bounds: default(),
+            bounds: Bounds { side },
             coll_events: ActiveEvents::COLLISION_EVENTS,
         })
         .insert(Restitution::coefficient(1.0))
@@ -1,88 +1,374 @@
-use crate::utils::{approx_eq, inverse_lerp, lerp, random_horizontal, rotate_vec2};
+use crate::utils::{inverse_lerp, lerp, random_horizontal, rotate_vec2, sign_or_zero};
 
 use super::{
-    game_entities::*, input,
+    ai::*,
+    config::{PaddleConfig, PongConfig},
+    game_entities::*,
+    game_setup_systems::{spawn_ball, spawn_launched_ball},
+    input,
+    powerups::{ActiveEffects, ExtraBall, ReversedControls},
+    rng::GameRng,
+    trajectory::{predict_intercept, ArenaBounds},
+};
+use bevy::{
+    prelude::*,
+    render::camera::ScalingMode,
+    window::{WindowId, WindowResized},
 };
-use bevy::prelude::*;
 use bevy_rapier2d::prelude::*;
-use rand::random;
+use iyes_loopless::prelude::*;
+use rand::Rng;
+use std::time::Duration;
 
-pub fn ball_launch_timer(
-    time: Res<Time>,
-    mut launch_ev: EventReader<BallLaunch>,
-    mut timer: ResMut<BallLaunchDelay>,
+pub fn advance_game_clock(mut clock: ResMut<GameClock>) {
+    let delta = clock.step;
+    clock.delta = delta;
+    clock.elapsed += delta;
+    clock.tick += 1;
+}
+
+/// Scales the game camera so the whole arena stays visible, leaving black bars on the sides
+/// that don't match the window's aspect ratio.
+pub fn fit_camera_to_window(
+    arena: Res<Arena>,
+    windows: Res<Windows>,
+    mut resized_ev: EventReader<WindowResized>,
+    mut query: Query<&mut OrthographicProjection, With<GameCamera>>,
+    added_query: Query<(), Added<GameCamera>>,
 ) {
-    for _ in launch_ev.iter() {
-        timer.0.reset();
+    let resized = resized_ev
+        .iter()
+        .rfind(|x| x.id == WindowId::primary())
+        .map(|x| Vec2::new(x.width, x.height));
+    if resized.is_none() && !arena.is_changed() && added_query.is_empty() {
+        return;
     }
 
-    timer.0.tick(time.delta());
+    let window_size = match resized.or_else(|| {
+        windows
+            .get_primary()
+            .map(|x| Vec2::new(x.width(), x.height()))
+    }) {
+        Some(size) if size.x > 0.0 && size.y > 0.0 => size,
+        _ => return,
+    };
+    let scale = (arena.size() / window_size).max_element();
+    for mut projection in query.iter_mut() {
+        projection.scaling_mode = ScalingMode::WindowSize;
+        projection.scale = scale;
+    }
 }
 
-pub fn is_ball_launch_ready(timer: Res<BallLaunchDelay>) -> bool {
-    timer.0.just_finished()
+/// Pushes a changed `PongConfig` onto the arena and everything already spawned from it.
+pub fn apply_config(
+    config: Res<PongConfig>,
+    mut arena: ResMut<Arena>,
+    mut ball_query: Query<(&mut Ball, &mut BallLaunchTimer, &mut Sprite, &mut Collider)>,
+    mut paddle_query: Query<(&mut Paddle, &mut Sprite, &mut Collider), Without<Ball>>,
+) {
+    if !config.is_changed() {
+        return;
+    }
+
+    if *arena != config.arena {
+        *arena = config.arena.clone();
+    }
+    let radius = config.ball.radius;
+    for (mut ball, mut timer, mut sprite, mut collider) in ball_query.iter_mut() {
+        *ball = Ball::from(&config.ball);
+        timer
+            .0
+            .set_duration(Duration::from_secs_f32(config.ball.launch_delay));
+        sprite.custom_size = Some(Vec2::splat(radius * 2.0));
+        *collider = Collider::ball(radius);
+    }
+    let size = config.paddle.size;
+    for (mut paddle, mut sprite, mut collider) in paddle_query.iter_mut() {
+        *paddle = Paddle::from(&config.paddle);
+        sprite.custom_size = Some(size);
+        *collider = Collider::cuboid(size.x / 2.0, size.y / 2.0);
+    }
 }
 
-pub fn was_ball_launched(timer: Res<BallLaunchDelay>) -> bool {
-    timer.0.finished()
+/// Moves walls, goals and paddles to where the current `Arena` wants them.
+pub fn layout_arena(
+    mut commands: Commands,
+    arena: Res<Arena>,
+    mut bounds_query: Query<(
+        &Bounds,
+        Option<&GoalPost>,
+        &mut Transform,
+        &mut Sprite,
+        &mut Collider,
+    )>,
+    mut goal_query: Query<(&Goal, &mut Transform, &mut Collider), Without<Bounds>>,
+    mut paddle_query: Query<
+        (&input::PlayerId, &mut Transform),
+        (With<Paddle>, Without<Bounds>, Without<Goal>),
+    >,
+) {
+    if !arena.is_changed() {
+        return;
+    }
+
+    commands.insert_resource(arena.bounds());
+    for (bounds, post, mut transform, mut sprite, mut collider) in bounds_query.iter_mut() {
+        let (size, position) = match post {
+            Some(post) => arena.goal_post(bounds.side, post.end),
+            None => arena.wall(bounds.side),
+        };
+        transform.translation = position.extend(transform.translation.z);
+        sprite.custom_size = Some(size);
+        *collider = Collider::cuboid(size.x / 2.0, size.y / 2.0);
+    }
+    for (goal, mut transform, mut collider) in goal_query.iter_mut() {
+        let (size, position) = arena.goal_zone(goal.side);
+        transform.translation = position.extend(transform.translation.z);
+        *collider = Collider::cuboid(size.x / 2.0, size.y / 2.0);
+    }
+    for (player, mut transform) in paddle_query.iter_mut() {
+        transform.translation.x = arena.paddle_x(*player);
+    }
 }
 
-pub fn start_ball_movement(mut query: Query<(&Ball, &mut Velocity, &mut Transform)>) {
-    for (ball, mut velocity, mut transform) in query.iter_mut() {
-        set_initial_ball_position(&mut transform);
-        launch_ball(ball, &mut velocity);
+pub fn ball_launch_timer(
+    clock: Res<GameClock>,
+    mut launch_ev: EventReader<BallLaunch>,
+    mut query: Query<&mut BallLaunchTimer>,
+) {
+    for ev in launch_ev.iter() {
+        if let Ok(mut timer) = query.get_mut(ev.ball) {
+            timer.0.reset();
+        }
+    }
+
+    for mut timer in query.iter_mut() {
+        timer.0.tick(clock.delta);
+    }
+}
+
+pub fn start_ball_movement(
+    mut rng: ResMut<GameRng>,
+    mut query: Query<(&Ball, &BallLaunchTimer, &mut Velocity)>,
+) {
+    for (ball, timer, mut velocity) in query.iter_mut() {
+        if timer.0.just_finished() {
+            launch_ball(ball, &mut velocity, &mut *rng);
+        }
+    }
+}
+
+pub fn handle_spawn_ball(
+    mut commands: Commands,
+    mut spawn_ev: EventReader<SpawnBall>,
+    assets: Res<AssetServer>,
+    config: Res<PongConfig>,
+) {
+    for ev in spawn_ev.iter() {
+        match ev.velocity {
+            Some(velocity) => {
+                spawn_launched_ball(&mut commands, &assets, &config, ev.position, velocity)
+            }
+            None => spawn_ball(&mut commands, &assets, &config, ev.position),
+        };
     }
 }
 
 pub fn paddle_movement(
+    clock: Res<GameClock>,
     axis_inputs: Query<&input::InputAxes>,
-    mut query: Query<(&mut Velocity, &Paddle), With<PlayerPaddle>>,
+    mut query: Query<
+        (
+            &mut Velocity,
+            &mut PaddleMotion,
+            &Paddle,
+            &input::PlayerId,
+            Option<&ReversedControls>,
+        ),
+        With<PlayerPaddle>,
+    >,
 ) {
-    let input = axis_inputs.single();
-    let vertical_input = input.val.get(&input::Axis::Vertical).unwrap();
-    for (mut rb, paddle) in query.iter_mut() {
-        rb.linvel.y = vertical_input.val * paddle.speed;
+    let dt = clock.step.as_secs_f32();
+    let vertical_input =
+        |axes: &input::InputAxes| axes.val.get(&input::Axis::Vertical).map_or(0.0, |x| x.val);
+    // keys of players without a paddle of their own, like player two's against the AI, steer
+    // player one too
+    let unclaimed_input: f32 = axis_inputs
+        .iter()
+        .filter(|axes| !query.iter().any(|x| *x.3 == axes.player))
+        .map(vertical_input)
+        .sum();
+
+    for (mut rb, mut motion, paddle, player, reversed) in query.iter_mut() {
+        let mut input = axis_inputs
+            .iter()
+            .find(|x| x.player == *player)
+            .map_or(0.0, vertical_input);
+        if *player == input::PlayerId::One {
+            input = (input + unclaimed_input).clamp(-1.0, 1.0);
+        }
+        let direction = if reversed.is_some() { -1.0 } else { 1.0 };
+        rb.linvel.y = motion.step(paddle, rb.linvel.y, input * direction, dt);
     }
 }
 
-pub fn evaluate_ball_collision(
-    mut ev_goal: EventWriter<GoalEvent>,
-    mut ball_query: Query<(Entity, &Transform, &mut Velocity, &Ball)>,
-    paddle_query: Query<(Entity, &Transform, &Collider), With<Paddle>>,
-    goal_query: Query<(Entity, &Goal)>,
-    rapier_context: Res<RapierContext>,
-) {
-    for (b_entity, b_transform, mut b_velocity, b) in ball_query.iter_mut() {
-        for contact_pair in rapier_context.contacts_with(b_entity) {
-            let other = if b_entity == contact_pair.collider1() {
-                contact_pair.collider2()
-            } else {
-                contact_pair.collider1()
-            };
+/// Keeps every paddle between the walls. Velocities are cut so the next physics step
+/// ends exactly at the wall at most, which holds at any speed and step size.
+pub fn constrain_paddles(
+    clock: Res<GameClock>,
+    arena: Res<ArenaBounds>,
+    mut query: Query<(&mut Transform, &mut Velocity, &Collider), With<Paddle>>,
+) {
+    let dt = clock.step.as_secs_f32();
+
+    for (mut transform, mut velocity, collider) in query.iter_mut() {
+        let half_height = collider.as_cuboid().map_or(0.0, |x| x.half_extents().y);
+        let min_y = arena.min.y + half_height;
+        let max_y = (arena.max.y - half_height).max(min_y);
+
+        let y = transform.translation.y;
+        if y < min_y || y > max_y {
+            // only after the paddle or arena changed size, velocities never take it out
+            transform.translation.y = y.clamp(min_y, max_y);
+        }
+        if dt > 0.0 {
+            let y = transform.translation.y;
+            velocity.linvel.y = velocity.linvel.y.clamp((min_y - y) / dt, (max_y - y) / dt);
+        }
+    }
+}
 
-            let opt_goal = || goal_query.iter().find(|x| x.0 == other);
-            let opt_paddle = paddle_query.iter().find(|x| x.0 == other);
-            if let Some((_, p_transform, p_collider)) = opt_paddle {
-                handle_ball_paddle_collision(
-                    p_transform,
-                    p_collider,
-                    b_transform,
-                    &mut b_velocity,
-                    b,
-                );
-            } else if let Some((_, goal)) = opt_goal() {
-                handle_ball_goal_collision(&mut ev_goal, goal, b_entity.id());
+pub fn apply_match_mode(
+    mut commands: Commands,
+    mode: Res<MatchMode>,
+    difficulty: Res<AiDifficulty>,
+    query: Query<(Entity, &input::PlayerId), With<Paddle>>,
+) {
+    for (entity, player) in query.iter() {
+        if *player != input::PlayerId::Two {
+            continue;
+        }
+        match *mode {
+            MatchMode::VsAI => {
+                commands
+                    .entity(entity)
+                    .remove::<PlayerPaddle>()
+                    .insert(AIPaddle {
+                        difficulty: *difficulty,
+                        ..default()
+                    });
             }
+            MatchMode::TwoPlayer => {
+                commands
+                    .entity(entity)
+                    .remove::<AIPaddle>()
+                    .remove::<AIBrain>()
+                    .insert(PlayerPaddle);
+            }
+        }
+    }
+}
+
+pub fn collect_started_contacts(
+    mut collision_ev: EventReader<CollisionEvent>,
+    mut contacts: ResMut<StartedContacts>,
+) {
+    for ev in collision_ev.iter() {
+        if let CollisionEvent::Started(a, b, _) = ev {
+            contacts.0.push((*a, *b));
+        }
+    }
+}
+
+/// Turns the contacts that started during the last step into ball events, once per contact
+/// however long it lasts.
+#[allow(clippy::too_many_arguments)]
+pub fn detect_ball_collisions(
+    mut contacts: ResMut<StartedContacts>,
+    mut paddle_hit_ev: EventWriter<BallHitPaddle>,
+    mut wall_hit_ev: EventWriter<BallHitWall>,
+    mut goal_entered_ev: EventWriter<BallEnteredGoal>,
+    ball_query: Query<(), With<Ball>>,
+    paddle_query: Query<(), With<Paddle>>,
+    goal_query: Query<(), With<Goal>>,
+    wall_query: Query<(), With<Bounds>>,
+) {
+    for (a, b) in contacts.0.drain(..) {
+        let (ball, other) = if ball_query.contains(a) {
+            (a, b)
+        } else if ball_query.contains(b) {
+            (b, a)
+        } else {
+            continue;
+        };
+
+        if paddle_query.contains(other) {
+            paddle_hit_ev.send(BallHitPaddle {
+                ball,
+                paddle: other,
+            });
+        } else if goal_query.contains(other) {
+            goal_entered_ev.send(BallEnteredGoal { ball, goal: other });
+        } else if wall_query.contains(other) {
+            wall_hit_ev.send(BallHitWall { ball });
+        }
+    }
+}
+
+pub fn bounce_off_paddles(
+    mut paddle_hit_ev: EventReader<BallHitPaddle>,
+    mut ball_query: Query<(&Transform, &mut Velocity, &Collider, &mut LastHitBy, &Ball)>,
+    paddle_query: Query<(&Transform, &Velocity, &Collider), (With<Paddle>, Without<Ball>)>,
+    config: Res<PongConfig>,
+    mut rng: ResMut<GameRng>,
+) {
+    for ev in paddle_hit_ev.iter() {
+        if let (
+            Ok((b_transform, mut b_velocity, b_collider, mut last_hit, b)),
+            Ok((p_transform, p_velocity, p_collider)),
+        ) = (ball_query.get_mut(ev.ball), paddle_query.get(ev.paddle))
+        {
+            last_hit.0 = Some(ev.paddle);
+            handle_ball_paddle_collision(
+                p_transform,
+                p_velocity,
+                p_collider,
+                b_transform,
+                &mut b_velocity,
+                b_collider,
+                b,
+                &config.paddle,
+                &mut *rng,
+            );
+        }
+    }
+}
+
+pub fn enter_goals(
+    mut goal_entered_ev: EventReader<BallEnteredGoal>,
+    mut ev_goal: EventWriter<GoalEvent>,
+    goal_query: Query<&Goal>,
+) {
+    for ev in goal_entered_ev.iter() {
+        if let Ok(goal) = goal_query.get(ev.goal) {
+            handle_ball_goal_collision(&mut ev_goal, goal, ev.ball);
         }
     }
 }
 
+/// Sends the ball back at an angle set by where it hit the paddle, bent further and spun by
+/// the paddle's own motion.
+#[allow(clippy::too_many_arguments)]
 pub fn handle_ball_paddle_collision(
     p_transform: &Transform,
+    p_velocity: &Velocity,
     p_collider: &Collider,
     b_transform: &Transform,
     b_velocity: &mut Velocity,
+    b_collider: &Collider,
     b: &Ball,
+    paddle_config: &PaddleConfig,
+    rng: &mut impl Rng,
 ) {
     let col_extents = p_collider.as_cuboid().unwrap().half_extents();
 
@@ -90,22 +376,31 @@ pub fn handle_ball_paddle_collision(
     let p_max = p_transform.translation.truncate() + col_extents;
     let b_position = b_transform.translation.truncate();
 
-    const MIN_ANGLE: f32 = 45.0_f32;
-    const MAX_ANGLE: f32 = -45.0_f32;
+    let max_angle = paddle_config.max_bounce_angle;
     let reflection_ratio = inverse_lerp(p_min.y, p_max.y, b_position.y);
-    let mut reflection_radians = lerp(MIN_ANGLE, MAX_ANGLE, reflection_ratio).to_radians();
+    let mut reflection_radians = lerp(max_angle, -max_angle, reflection_ratio).to_radians();
     if reflection_radians != 0.0 {
         // a bit of noise to prevent the ball from always hitting the same spot
-        reflection_radians += random::<f32>().to_radians();
+        reflection_radians += (rng.gen::<f32>() * paddle_config.bounce_noise).to_radians();
     }
 
-    b_velocity.linvel = rotate_vec2(b_velocity.linvel * b.speed_multiplier, reflection_radians);
+    let linvel = rotate_vec2(b_velocity.linvel * b.speed_multiplier, reflection_radians);
+    // english bends the shot without making it any faster
+    let english = Vec2::new(0.0, p_velocity.linvel.y * paddle_config.english);
+    b_velocity.linvel = (linvel + english).normalize_or_zero() * linvel.length();
+
+    // the paddle's surface drags along the side of the ball it touches
+    let radius = b_collider.as_ball().map_or(0.0, |x| x.radius());
+    if radius > 0.0 {
+        let contact = Vec2::new(sign_or_zero(p_transform.translation.x - b_position.x), 0.0);
+        b_velocity.angvel = contact.perp_dot(p_velocity.linvel) * paddle_config.spin / radius;
+    }
 }
 
-pub fn handle_ball_goal_collision(ev_goal: &mut EventWriter<GoalEvent>, goal: &Goal, ball_id: u32) {
+pub fn handle_ball_goal_collision(ev_goal: &mut EventWriter<GoalEvent>, goal: &Goal, ball: Entity) {
     ev_goal.send(GoalEvent {
         team: goal.team.opposite(),
-        ball_id: ball_id,
+        ball,
     });
 }
 
@@ -117,67 +412,309 @@ pub fn score(mut ev_goal: EventReader<GoalEvent>, mut query: Query<&mut MatchSco
     }
 }
 
+pub fn check_victory(
+    mut commands: Commands,
+    mut ev_goal: EventReader<GoalEvent>,
+    mut ev_won: EventWriter<MatchWon>,
+    win_condition: Res<WinCondition>,
+    query: Query<&MatchScore>,
+) {
+    if ev_goal.iter().count() == 0 {
+        return;
+    }
+
+    if let Some(team) = win_condition.winner(query.single()) {
+        ev_won.send(MatchWon { team });
+        commands.insert_resource(NextState(GameState::GameOver));
+    }
+}
+
+pub fn freeze_match(mut query: Query<&mut Velocity, Or<(With<Ball>, With<Paddle>)>>) {
+    for mut velocity in query.iter_mut() {
+        *velocity = Velocity::zero();
+    }
+}
+
+pub fn set_physics_active(active: bool) -> impl Fn(ResMut<RapierConfiguration>) {
+    move |mut physics: ResMut<RapierConfiguration>| {
+        physics.physics_pipeline_active = active;
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn reset_match(
+    mut commands: Commands,
+    assets: Res<AssetServer>,
+    config: Res<PongConfig>,
+    count: Res<BallCount>,
+    mut clock: ResMut<GameClock>,
+    mut rng: ResMut<GameRng>,
+    mut contacts: ResMut<StartedContacts>,
+    mut score_query: Query<&mut MatchScore>,
+    ball_query: Query<Entity, With<Ball>>,
+    mut paddle_query: Query<(&mut Velocity, &mut Transform, &mut PaddleMotion), With<Paddle>>,
+    mut ai_query: Query<(Entity, &mut AIPaddle)>,
+) {
+    for mut match_score in score_query.iter_mut() {
+        for team_score in match_score.score.values_mut() {
+            *team_score = 0;
+        }
+    }
+    // every match replays the same random sequence for its seed, on the same timeline
+    rng.reset();
+    clock.elapsed = Duration::ZERO;
+    // e.g. the winning goal, touched during the last step of the previous match
+    contacts.0.clear();
+    // balls spawned mid-match don't survive a restart
+    for entity in ball_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    for _ in 0..count.0 {
+        spawn_ball(&mut commands, &assets, &config, Vec2::ZERO);
+    }
+    for (mut velocity, mut transform, mut motion) in paddle_query.iter_mut() {
+        *velocity = Velocity::zero();
+        *motion = default();
+        transform.translation.y = 0.0;
+    }
+    // `update_ai_brains` builds a fresh brain, without the last match's reaction timing
+    for (entity, mut ai_paddle) in ai_query.iter_mut() {
+        *ai_paddle = AIPaddle {
+            difficulty: ai_paddle.difficulty,
+            ..default()
+        };
+        commands.entity(entity).remove::<AIBrain>();
+    }
+}
+
 pub fn reset_ball(
     mut launch_ev: EventWriter<BallLaunch>,
     mut ev_goal: EventReader<GoalEvent>,
-    mut ball_query: Query<(Entity, &mut Velocity, &mut Transform)>,
+    mut commands: Commands,
+    bounds: Res<ArenaBounds>,
+    mut ball_query: Query<
+        (
+            &mut Velocity,
+            &mut Transform,
+            &mut BallLaunchTimer,
+            &mut ActiveEffects,
+            &Collider,
+            Option<&ExtraBall>,
+        ),
+        With<Ball>,
+    >,
 ) {
+    let mut waiting = None;
     for ev in ev_goal.iter() {
-        let (_, mut velocity, mut transform) = ball_query
-            .iter_mut()
-            .find(|x| x.0.id() == ev.ball_id)
-            .unwrap();
-        set_initial_ball_position(&mut transform);
+        let waiting = waiting.get_or_insert_with(|| {
+            ball_query
+                .iter()
+                .filter(|(_, _, timer, ..)| !timer.0.finished())
+                .count()
+        });
+        let (mut velocity, mut transform, mut timer, mut effects, collider, extra) =
+            match ball_query.get_mut(ev.ball) {
+                Ok(ball) => ball,
+                Err(_) => continue,
+            };
+        // a relaunched ball starts over, nothing is left to restore on it
+        for entity in effects.0.drain(..) {
+            commands.entity(entity).despawn();
+        }
+        if extra.is_some() {
+            commands.entity(ev.ball).despawn_recursive();
+            continue;
+        }
+        let radius = collider.as_ball().map_or(0.0, |x| x.radius());
+        set_waiting_ball_position(&mut transform, *waiting, radius, &bounds);
+        *waiting += 1;
         set_initial_ball_speed(&mut velocity);
-        launch_ev.send(BallLaunch);
+        // waiting from this tick on, so `limit_ball_velocity` leaves it standing still
+        // whichever of the two runs first
+        timer.0.reset();
+        launch_ev.send(BallLaunch { ball: ev.ball });
     }
 }
 
-pub fn set_initial_ball_position(mut transform: &mut Transform) {
-    transform.translation = Vec3::ZERO;
+const GOLDEN_ANGLE: f32 = 2.399_963;
+
+/// Puts the `slot`th ball waiting for launch in the middle of the arena if it's the first, or
+/// on a spiral around the middle clear of the others. Stacked on one spot, the physics broad
+/// phase would pair every waiting ball with every other. Slots past what fits in the arena
+/// start over from the middle.
+pub fn set_waiting_ball_position(
+    transform: &mut Transform,
+    slot: usize,
+    radius: f32,
+    bounds: &ArenaBounds,
+) {
+    // each turn of the golden angle out to the root of the slot adds the same area
+    let spacing = radius * 3.0;
+    let reach = ((bounds.max - bounds.min).min_element() / 2.0 - radius).max(0.0);
+    let slots = ((reach / spacing).powi(2) as usize).max(1);
+    let slot = slot % slots;
+    let angle = slot as f32 * GOLDEN_ANGLE;
+    let offset = Vec2::new(angle.cos(), angle.sin()) * spacing * (slot as f32).sqrt();
+    transform.translation = ((bounds.min + bounds.max) / 2.0 + offset).extend(0.0);
     transform.rotation = Quat::IDENTITY;
 }
 
-pub fn set_initial_ball_speed(mut velocity: &mut Velocity) {
+pub fn set_initial_ball_speed(velocity: &mut Velocity) {
     velocity.linvel = Vec2::ZERO;
     velocity.angvel = 0.0;
 }
 
-pub fn launch_ball(ball: &Ball, mut velocity: &mut Velocity) {
-    velocity.linvel = random_horizontal() * ball.initial_speed;
+pub fn launch_ball(ball: &Ball, velocity: &mut Velocity, rng: &mut impl Rng) {
+    velocity.linvel = random_horizontal(rng) * ball.initial_speed;
+}
+
+/// Curves the flight of spinning balls, Magnus style, by turning their velocity without
+/// changing their speed, while the spin wears off.
+pub fn curve_spinning_balls(clock: Res<GameClock>, mut query: Query<(&mut Velocity, &Ball)>) {
+    let dt = clock.step.as_secs_f32();
+    for (mut velocity, ball) in query.iter_mut() {
+        if velocity.angvel == 0.0 {
+            continue;
+        }
+        velocity.linvel = rotate_vec2(velocity.linvel, velocity.angvel * ball.magnus * dt);
+        velocity.angvel *= (1.0 - ball.spin_decay).powf(dt);
+    }
+}
+
+pub fn limit_ball_velocity(mut query: Query<(&mut Velocity, &Ball, &BallLaunchTimer)>) {
+    for (mut v, ball, timer) in query.iter_mut() {
+        // balls waiting for launch are meant to stand still
+        if timer.0.finished() {
+            v.linvel = ball.limit_velocity(v.linvel);
+        }
+    }
 }
 
-pub fn prevent_stuck_ball(mut query: Query<&mut Velocity, With<Ball>>) {
-    const MIN_V: f32 = 100.0;
-    for mut v in query.iter_mut() {
-        if approx_eq(v.linvel.x, 0.0, MIN_V) {
-            v.linvel.x += MIN_V * v.linvel.x.signum();
+/// Points every AI paddle at the ball that will reach it first, falling back to the closest
+/// ball when none is heading its way.
+pub fn select_ai_targets(
+    arena: Res<ArenaBounds>,
+    ball_query: Query<(Entity, &Transform, &Velocity, &Collider, &BallLaunchTimer), With<Ball>>,
+    mut paddle_query: Query<(&mut AIPaddle, &Transform), Without<Ball>>,
+) {
+    for (mut ai_paddle, paddle_transform) in paddle_query.iter_mut() {
+        let paddle_x = paddle_transform.translation.x;
+        let threat = |(_, transform, velocity, collider, timer): &(
+            Entity,
+            &Transform,
+            &Velocity,
+            &Collider,
+            &BallLaunchTimer,
+        )| {
+            if !timer.0.finished() {
+                return None;
+            }
+            let radius = collider.as_ball().map_or(0.0, |x| x.radius());
+            predict_intercept(
+                transform.translation.truncate(),
+                velocity,
+                radius,
+                &arena,
+                paddle_x,
+            )
+            .map(|x| x.time)
+        };
+
+        let most_threatening = ball_query
+            .iter()
+            .filter_map(|x| threat(&x).map(|time| (x.0, time)))
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|x| x.0);
+        let closest = || {
+            ball_query
+                .iter()
+                .min_by(|a, b| {
+                    let a_dist = (a.1.translation.x - paddle_x).abs();
+                    let b_dist = (b.1.translation.x - paddle_x).abs();
+                    a_dist.total_cmp(&b_dist)
+                })
+                .map(|x| x.0)
+        };
+
+        if let Some(target) = most_threatening.or_else(closest) {
+            ai_paddle.target_ball = Some(target);
         }
     }
 }
 
-pub fn limit_ball_velocity(mut query: Query<(&mut Velocity, &Ball)>) {
-    for (mut v, ball) in query.iter_mut() {
-        v.linvel = Vec2::min(v.linvel, ball.max_speed);
+pub fn update_ai_brains(
+    mut commands: Commands,
+    query: Query<(Entity, &AIPaddle, Option<&AIBrain>)>,
+) {
+    for (entity, ai_paddle, brain) in query.iter() {
+        if !matches!(brain, Some(x) if x.difficulty == ai_paddle.difficulty) {
+            commands.entity(entity).insert(AIBrain {
+                difficulty: ai_paddle.difficulty,
+                controller: ai_paddle.difficulty.controller(),
+            });
+        }
     }
 }
 
 pub fn enemy_paddle_movement(
-    ball_query: Query<(Entity, &Transform), With<Ball>>,
-    mut paddle_query: Query<(&Paddle, &AIPaddle, &Transform, &mut Velocity)>,
+    clock: Res<GameClock>,
+    mut rng: ResMut<GameRng>,
+    arena: Res<ArenaBounds>,
+    ball_query: Query<(&Transform, &Velocity, &Collider), With<Ball>>,
+    mut paddle_query: Query<
+        (
+            &Paddle,
+            &mut PaddleMotion,
+            &mut AIPaddle,
+            &mut AIBrain,
+            &Transform,
+            &mut Velocity,
+            Option<&ReversedControls>,
+        ),
+        Without<Ball>,
+    >,
 ) {
-    let (paddle, ai_paddle, paddle_transform, mut paddle_vel) = paddle_query.single_mut();
-    let (_, ball_transform) = ball_query
-        .iter()
-        .find(|x| x.0.id() == ai_paddle.target_ball)
-        .or(ball_query.iter().next())
-        .unwrap();
-
-    let y_diff = ball_transform.translation.y - paddle_transform.translation.y;
-    const MAX_DELTA: f32 = 45.0_f32;
-    if approx_eq(y_diff, 0.0, MAX_DELTA) {
-        paddle_vel.linvel.y = 0.0;
-    } else {
-        paddle_vel.linvel.y = y_diff.signum() * paddle.speed;
+    let elapsed = clock.elapsed.as_secs_f32();
+    let dt = clock.step.as_secs_f32();
+    for (
+        paddle,
+        mut motion,
+        mut ai_paddle,
+        mut brain,
+        paddle_transform,
+        mut paddle_vel,
+        reversed,
+    ) in paddle_query.iter_mut()
+    {
+        let (ball_transform, ball_velocity, ball_collider) = match ai_paddle
+            .target_ball
+            .and_then(|x| ball_query.get(x).ok())
+            .or_else(|| ball_query.iter().next())
+        {
+            Some(ball) => ball,
+            None => continue,
+        };
+
+        let ctx = AiContext {
+            paddle_position: paddle_transform.translation.truncate(),
+            paddle_velocity: paddle_vel.linvel.y,
+            paddle_deceleration: paddle.deceleration,
+            ball_position: ball_transform.translation.truncate(),
+            ball_velocity: ball_velocity.linvel,
+            ball_radius: ball_collider.as_ball().map_or(0.0, |x| x.radius()),
+            arena: *arena,
+            elapsed,
+            time_since_velocity_change: elapsed - ai_paddle.last_velocity_change_time,
+        };
+        let mut input = brain.controller.decide(&ctx, &mut rng).clamp(-1.0, 1.0);
+        if reversed.is_some() {
+            input = -input;
+        }
+
+        let linvel_y = motion.step(paddle, paddle_vel.linvel.y, input, dt);
+        if sign_or_zero(linvel_y) != sign_or_zero(paddle_vel.linvel.y) {
+            ai_paddle.last_velocity_change_time = elapsed;
+        }
+        paddle_vel.linvel.y = linvel_y;
     }
 }
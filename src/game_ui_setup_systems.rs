@@ -53,3 +53,96 @@ pub fn spawn_hud(mut commands: Commands, asset_server: Res<AssetServer>) {
                 .insert(ScoreText { team: Team::AI });
         });
 }
+
+pub fn spawn_main_menu(commands: Commands, asset_server: Res<AssetServer>) {
+    spawn_menu(
+        commands,
+        &asset_server,
+        "PONG",
+        "1: vs AI    2: two players    Esc: quit",
+    );
+}
+
+pub fn spawn_pause_menu(commands: Commands, asset_server: Res<AssetServer>) {
+    spawn_menu(
+        commands,
+        &asset_server,
+        "Paused",
+        "Esc: resume    M: main menu",
+    );
+}
+
+pub fn spawn_game_over_menu(
+    commands: Commands,
+    asset_server: Res<AssetServer>,
+    win_condition: Res<WinCondition>,
+    mode: Res<MatchMode>,
+    score_query: Query<&MatchScore>,
+) {
+    let title = match (win_condition.winner(score_query.single()), *mode) {
+        (Some(Team::Player), MatchMode::VsAI) => "You win!",
+        (Some(Team::AI), MatchMode::VsAI) => "You lose!",
+        (Some(Team::Player), MatchMode::TwoPlayer) => "Player 1 wins!",
+        (Some(Team::AI), MatchMode::TwoPlayer) => "Player 2 wins!",
+        (None, _) => "Game Over",
+    };
+    spawn_menu(
+        commands,
+        &asset_server,
+        title,
+        "Space: play again    M: main menu",
+    );
+}
+
+fn spawn_menu(mut commands: Commands, asset_server: &AssetServer, title: &str, hint: &str) {
+    let font = asset_server.load(FONT_ASSET);
+    let alignment = TextAlignment {
+        horizontal: HorizontalAlign::Center,
+        vertical: VerticalAlign::Center,
+    };
+
+    commands
+        .spawn_bundle(NodeBundle {
+            color: Color::NONE.into(),
+            style: Style {
+                position_type: PositionType::Absolute,
+                size: Size {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                },
+                flex_direction: FlexDirection::ColumnReverse,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            ..default()
+        })
+        .insert(Menu)
+        .with_children(|parent| {
+            parent.spawn_bundle(TextBundle {
+                text: Text::with_section(
+                    title,
+                    TextStyle {
+                        font: font.clone(),
+                        font_size: 150.0,
+                        color: Color::WHITE,
+                    },
+                    alignment,
+                ),
+                ..default()
+            });
+
+            parent.spawn_bundle(TextBundle {
+                text: Text::with_section(
+                    hint,
+                    TextStyle {
+                        font,
+                        font_size: 40.0,
+                        color: Color::WHITE,
+                    },
+                    alignment,
+                ),
+                ..default()
+            });
+        });
+}
@@ -1,17 +1,23 @@
 use super::game_entities::*;
-use bevy::prelude::*;
+use bevy::{app::AppExit, prelude::*};
+use iyes_loopless::prelude::*;
 
 pub fn update_ball_launch_timer(
-    timer: Res<BallLaunchDelay>,
+    timer_query: Query<&BallLaunchTimer>,
     mut query: Query<(&mut Visibility, &mut Text), With<BallLaunchTimerText>>,
 ) {
+    let next_launch = timer_query
+        .iter()
+        .filter(|x| !x.0.finished())
+        .map(|x| x.0.duration().as_secs_f32() - x.0.elapsed_secs())
+        .reduce(f32::min);
     for (mut visibility, mut text) in query.iter_mut() {
-        if timer.0.finished() {
-            visibility.is_visible = false;
-        } else {
-            visibility.is_visible = true;
-            let remaining = timer.0.duration().as_secs_f32() - timer.0.elapsed_secs();
-            text.sections[0].value = format!("{:0.1}", remaining);
+        match next_launch {
+            None => visibility.is_visible = false,
+            Some(remaining) => {
+                visibility.is_visible = true;
+                text.sections[0].value = format!("{:0.1}", remaining);
+            }
         }
     }
 }
@@ -20,23 +26,65 @@ pub fn initial_score(
     score_query: Query<&MatchScore>,
     mut text_query: Query<(&ScoreText, &mut Text)>,
 ) {
-    update_score(&score_query, &mut text_query);
+    update_score(score_query.single(), &mut text_query);
 }
 
 pub fn update_score_runtime(
-    mut ev_goal: EventReader<GoalEvent>,
-    score_query: Query<&MatchScore>,
+    score_query: Query<&MatchScore, Changed<MatchScore>>,
     mut text_query: Query<(&ScoreText, &mut Text)>,
 ) {
-    for _ in ev_goal.iter() {
-        update_score(&score_query, &mut text_query);
+    for match_score in score_query.iter() {
+        update_score(match_score, &mut text_query);
     }
 }
 
-fn update_score(score_query: &Query<&MatchScore>, text_query: &mut Query<(&ScoreText, &mut Text)>) {
-    let match_score = score_query.single();
+fn update_score(match_score: &MatchScore, text_query: &mut Query<(&ScoreText, &mut Text)>) {
     for (score_text, mut text) in text_query.iter_mut() {
         let team_score = match_score.score.get(&score_text.team).unwrap();
         text.sections[0].value = team_score.to_string();
     }
 }
+
+pub fn main_menu_input(
+    mut commands: Commands,
+    keyboard_input: Res<Input<KeyCode>>,
+    mut app_exit: EventWriter<AppExit>,
+) {
+    if keyboard_input.any_just_pressed([KeyCode::Space, KeyCode::Return, KeyCode::Key1]) {
+        commands.insert_resource(MatchMode::VsAI);
+        commands.insert_resource(NextState(GameState::Playing));
+    } else if keyboard_input.just_pressed(KeyCode::Key2) {
+        commands.insert_resource(MatchMode::TwoPlayer);
+        commands.insert_resource(NextState(GameState::Playing));
+    } else if keyboard_input.just_pressed(KeyCode::Escape) {
+        app_exit.send(AppExit);
+    }
+}
+
+pub fn pause_input(mut commands: Commands, keyboard_input: Res<Input<KeyCode>>) {
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        commands.insert_resource(NextState(GameState::Paused));
+    }
+}
+
+pub fn paused_input(mut commands: Commands, keyboard_input: Res<Input<KeyCode>>) {
+    if keyboard_input.any_just_pressed([KeyCode::Escape, KeyCode::Space]) {
+        commands.insert_resource(NextState(GameState::Playing));
+    } else if keyboard_input.just_pressed(KeyCode::M) {
+        commands.insert_resource(NextState(GameState::MainMenu));
+    }
+}
+
+pub fn game_over_input(mut commands: Commands, keyboard_input: Res<Input<KeyCode>>) {
+    if keyboard_input.any_just_pressed([KeyCode::Space, KeyCode::Return]) {
+        commands.insert_resource(NextState(GameState::Playing));
+    } else if keyboard_input.any_just_pressed([KeyCode::Escape, KeyCode::M]) {
+        commands.insert_resource(NextState(GameState::MainMenu));
+    }
+}
+
+pub fn despawn_menu(mut commands: Commands, query: Query<Entity, With<Menu>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
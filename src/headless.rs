@@ -0,0 +1,43 @@
+pub use super::game_entities::TICK_RATE;
+use super::{
+    config::PongConfigSource, game, game_entities::*, input, input_bindings::BindingsConfig,
+    rng::GameRng,
+};
+use bevy::{asset::AssetPlugin, input::InputPlugin, prelude::*, window::WindowPlugin};
+use bevy_rapier2d::prelude::*;
+use iyes_loopless::prelude::*;
+use std::time::Duration;
+
+pub const SEED: u64 = 0;
+
+/// Builds an app that runs `PongGame` without a window or renderer, skipping the main menu and
+/// using the default key bindings and game config, with randomness seeded from `SEED`.
+/// Every `App::update` advances gameplay and physics by exactly one tick of `1 / TICK_RATE`
+/// seconds, regardless of how long the update took.
+pub fn headless_app() -> App {
+    let tick = Duration::from_secs_f64(1.0 / TICK_RATE);
+    let mut app = App::new();
+    app.insert_resource(GameClock::per_update(tick))
+        .insert_resource(BindingsConfig { path: None })
+        .insert_resource(PongConfigSource { path: None })
+        .insert_resource(GameRng::new(SEED))
+        .add_plugins(MinimalPlugins)
+        .add_plugin(TransformPlugin)
+        .add_plugin(WindowPlugin::default())
+        .add_plugin(InputPlugin)
+        .add_plugin(AssetPlugin)
+        .add_plugin(
+            RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(game::PIXELS_PER_METER)
+                .with_default_system_setup(false),
+        )
+        .add_plugin(input::PongInput)
+        .add_plugin(game::PongGame)
+        .insert_resource(NextState(GameState::Playing));
+    app
+}
+
+pub fn run_ticks(app: &mut App, ticks: u32) {
+    for _ in 0..ticks {
+        app.update();
+    }
+}
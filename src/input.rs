@@ -1,15 +1,21 @@
+use super::input_bindings::*;
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 
 pub struct PongInput;
 impl Plugin for PongInput {
     fn build(&self, app: &mut App) {
-        app.add_startup_system(register_axes).add_system_set(
-            SystemSet::new()
-                .label(Label::Default)
-                .with_system(gather_input)
-                .with_system(bevy::input::system::exit_on_esc_system),
-        );
+        app.init_resource::<BindingsConfig>()
+            .add_event::<SaveBindings>()
+            .add_startup_system(register_axes)
+            .add_system_set(
+                SystemSet::new()
+                    .label(Label::Default)
+                    .with_system(assign_gamepads)
+                    .with_system(gather_input.after(assign_gamepads))
+                    .with_system(save_bindings),
+            );
     }
 }
 
@@ -18,18 +24,29 @@ pub enum Label {
     Default,
 }
 
-#[derive(Eq, PartialEq, Hash, Copy, Clone)]
+#[derive(Debug, Eq, PartialEq, Hash, Copy, Clone, Default, Component, Serialize, Deserialize)]
+pub enum PlayerId {
+    #[default]
+    One = 0,
+    Two = 1,
+}
+
+#[derive(Debug, Eq, PartialEq, Hash, Copy, Clone, Default, Serialize, Deserialize)]
 pub enum Axis {
+    #[default]
     Vertical = 0,
 }
-impl Default for Axis {
-    fn default() -> Self {
-        Axis::Vertical
-    }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AxisDirection {
+    Positive,
+    Negative,
 }
 
 #[derive(Default, Component)]
 pub struct InputAxes {
+    pub player: PlayerId,
+    pub gamepad: Option<Gamepad>,
     pub val: HashMap<Axis, InputAxis>,
 }
 
@@ -39,45 +56,156 @@ pub struct InputAxis {
     pub axis_id: Axis,
     pub positive_key_codes: HashSet<KeyCode>,
     pub negative_key_codes: HashSet<KeyCode>,
+    pub gamepad_axes: HashSet<GamepadAxisType>,
+    pub positive_gamepad_buttons: HashSet<GamepadButtonType>,
+    pub negative_gamepad_buttons: HashSet<GamepadButtonType>,
+    pub deadzone: f32,
+}
+
+impl InputAxes {
+    /// Binds `key` to one direction of `axis`, removing it from wherever else it was bound.
+    pub fn bind_key(&mut self, axis: Axis, direction: AxisDirection, key: KeyCode) {
+        self.unbind_key(key);
+        let input_axis = self.axis_mut(axis);
+        match direction {
+            AxisDirection::Positive => input_axis.positive_key_codes.insert(key),
+            AxisDirection::Negative => input_axis.negative_key_codes.insert(key),
+        };
+    }
+
+    pub fn unbind_key(&mut self, key: KeyCode) {
+        for input_axis in self.val.values_mut() {
+            input_axis.positive_key_codes.remove(&key);
+            input_axis.negative_key_codes.remove(&key);
+        }
+    }
+
+    /// Binds `button` to one direction of `axis`, removing it from wherever else it was bound.
+    pub fn bind_gamepad_button(
+        &mut self,
+        axis: Axis,
+        direction: AxisDirection,
+        button: GamepadButtonType,
+    ) {
+        self.unbind_gamepad_button(button);
+        let input_axis = self.axis_mut(axis);
+        match direction {
+            AxisDirection::Positive => input_axis.positive_gamepad_buttons.insert(button),
+            AxisDirection::Negative => input_axis.negative_gamepad_buttons.insert(button),
+        };
+    }
+
+    pub fn unbind_gamepad_button(&mut self, button: GamepadButtonType) {
+        for input_axis in self.val.values_mut() {
+            input_axis.positive_gamepad_buttons.remove(&button);
+            input_axis.negative_gamepad_buttons.remove(&button);
+        }
+    }
+
+    fn axis_mut(&mut self, axis: Axis) -> &mut InputAxis {
+        self.val.entry(axis).or_insert_with(|| InputAxis {
+            axis_id: axis,
+            deadzone: DEFAULT_DEADZONE,
+            ..default()
+        })
+    }
 }
 
 impl InputAxis {
     fn set_val(&mut self, v: f32) {
         self.val = v.clamp(-1.0, 1.0);
     }
-}
 
-fn register_axes(mut commands: Commands) {
-    let vertical_axis = InputAxis {
-        axis_id: Axis::Vertical,
-        positive_key_codes: HashSet::from([KeyCode::Up, KeyCode::W]),
-        negative_key_codes: HashSet::from([KeyCode::Down, KeyCode::S]),
-        ..default()
-    };
+    /// Zeroes `v` inside the deadzone and rescales the remainder back to the full range.
+    fn apply_deadzone(&self, v: f32) -> f32 {
+        if v.abs() <= self.deadzone {
+            0.0
+        } else {
+            v.signum() * (v.abs() - self.deadzone) / (1.0 - self.deadzone)
+        }
+    }
+}
 
-    let axes = InputAxes {
-        val: HashMap::from([(vertical_axis.axis_id, vertical_axis)]),
-    };
+fn register_axes(mut commands: Commands, config: Res<BindingsConfig>) {
+    for player_bindings in load_bindings(&config).players.iter() {
+        commands.spawn().insert(InputAxes::from(player_bindings));
+    }
+}
 
-    commands.spawn().insert(axes);
+/// Hands newly connected gamepads to the first player without one and frees the slot again
+/// when the gamepad is unplugged.
+fn assign_gamepads(
+    mut gamepad_ev: EventReader<GamepadEvent>,
+    mut input_axes: Query<&mut InputAxes>,
+) {
+    for GamepadEvent(gamepad, event_type) in gamepad_ev.iter() {
+        match event_type {
+            GamepadEventType::Connected => {
+                if input_axes.iter().any(|x| x.gamepad == Some(*gamepad)) {
+                    continue;
+                }
+                let mut free_axes: Vec<_> = input_axes
+                    .iter_mut()
+                    .filter(|x| x.gamepad.is_none())
+                    .collect();
+                free_axes.sort_by_key(|x| x.player as i32);
+                if let Some(axes) = free_axes.first_mut() {
+                    axes.gamepad = Some(*gamepad);
+                }
+            }
+            GamepadEventType::Disconnected => {
+                for mut axes in input_axes.iter_mut() {
+                    if axes.gamepad == Some(*gamepad) {
+                        axes.gamepad = None;
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
 }
 
 fn gather_input(
     keyboard_input: Res<bevy::input::Input<KeyCode>>,
+    gamepad_buttons: Res<bevy::input::Input<GamepadButton>>,
+    gamepad_axes: Res<bevy::input::Axis<GamepadAxis>>,
     mut input_axes: Query<&mut InputAxes>,
 ) {
     for mut axes in input_axes.iter_mut() {
+        let gamepad = axes.gamepad;
         for axis in axes.val.values_mut() {
-            let positive = axis
+            let mut positive = axis
                 .positive_key_codes
                 .iter()
                 .any(|k| keyboard_input.pressed(*k)) as i32 as f32;
-            let negative = axis
+            let mut negative = axis
                 .negative_key_codes
                 .iter()
                 .any(|k| keyboard_input.pressed(*k)) as i32 as f32;
 
-            axis.set_val(positive - negative);
+            let mut analog = 0.0;
+            if let Some(gamepad) = gamepad {
+                positive = positive.max(
+                    axis.positive_gamepad_buttons
+                        .iter()
+                        .any(|b| gamepad_buttons.pressed(GamepadButton(gamepad, *b)))
+                        as i32 as f32,
+                );
+                negative = negative.max(
+                    axis.negative_gamepad_buttons
+                        .iter()
+                        .any(|b| gamepad_buttons.pressed(GamepadButton(gamepad, *b)))
+                        as i32 as f32,
+                );
+                analog = axis
+                    .gamepad_axes
+                    .iter()
+                    .filter_map(|a| gamepad_axes.get(GamepadAxis(gamepad, *a)))
+                    .map(|v| axis.apply_deadzone(v))
+                    .fold(0.0, |acc: f32, v| if v.abs() > acc.abs() { v } else { acc });
+            }
+
+            axis.set_val(positive - negative + analog);
         }
     }
 }
@@ -0,0 +1,305 @@
+use super::input::{Axis, InputAxes, InputAxis, PlayerId};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+pub const DEFAULT_BINDINGS_PATH: &str = "config/bindings.ron";
+pub const DEFAULT_DEADZONE: f32 = 0.15;
+
+/// Where bindings are loaded from at startup and saved to on `SaveBindings`.
+/// `path: None` keeps the built-in defaults and never touches the disk.
+pub struct BindingsConfig {
+    pub path: Option<PathBuf>,
+}
+
+impl Default for BindingsConfig {
+    fn default() -> Self {
+        BindingsConfig {
+            path: Some(PathBuf::from(DEFAULT_BINDINGS_PATH)),
+        }
+    }
+}
+
+pub struct SaveBindings;
+
+#[derive(Debug)]
+pub enum BindingsError {
+    Io { path: PathBuf, source: io::Error },
+    Parse { path: PathBuf, source: ron::Error },
+    Serialize(ron::Error),
+    DuplicatePlayer(PlayerId),
+    DuplicateAxis(PlayerId, Axis),
+    InvalidDeadzone(PlayerId, Axis, f32),
+}
+
+impl fmt::Display for BindingsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BindingsError::Io { path, source } => {
+                write!(
+                    f,
+                    "could not access key bindings at {}: {}",
+                    path.display(),
+                    source
+                )
+            }
+            BindingsError::Parse { path, source } => {
+                write!(f, "invalid key bindings in {}: {}", path.display(), source)
+            }
+            BindingsError::Serialize(source) => {
+                write!(f, "could not serialize key bindings: {}", source)
+            }
+            BindingsError::DuplicatePlayer(player) => {
+                write!(f, "player {:?} is bound more than once", player)
+            }
+            BindingsError::DuplicateAxis(player, axis) => {
+                write!(
+                    f,
+                    "axis {:?} of player {:?} is bound more than once",
+                    axis, player
+                )
+            }
+            BindingsError::InvalidDeadzone(player, axis, deadzone) => write!(
+                f,
+                "deadzone {} of axis {:?} for player {:?} must be in [0, 1)",
+                deadzone, axis, player
+            ),
+        }
+    }
+}
+
+impl std::error::Error for BindingsError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            BindingsError::Io { source, .. } => Some(source),
+            BindingsError::Parse { source, .. } => Some(source),
+            BindingsError::Serialize(source) => Some(source),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AxisBinding {
+    pub axis: Axis,
+    #[serde(default)]
+    pub positive_keys: Vec<KeyCode>,
+    #[serde(default)]
+    pub negative_keys: Vec<KeyCode>,
+    #[serde(default)]
+    pub gamepad_axes: Vec<GamepadAxisType>,
+    #[serde(default)]
+    pub positive_gamepad_buttons: Vec<GamepadButtonType>,
+    #[serde(default)]
+    pub negative_gamepad_buttons: Vec<GamepadButtonType>,
+    #[serde(default = "default_deadzone")]
+    pub deadzone: f32,
+}
+
+fn default_deadzone() -> f32 {
+    DEFAULT_DEADZONE
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlayerBindings {
+    pub player: PlayerId,
+    pub axes: Vec<AxisBinding>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KeyBindings {
+    pub players: Vec<PlayerBindings>,
+}
+
+impl KeyBindings {
+    pub fn from_ron(path: &Path, contents: &str) -> Result<Self, BindingsError> {
+        let bindings: KeyBindings =
+            ron::from_str(contents).map_err(|source| BindingsError::Parse {
+                path: path.to_path_buf(),
+                source,
+            })?;
+        bindings.validate()?;
+        Ok(bindings)
+    }
+
+    pub fn to_ron(&self) -> Result<String, BindingsError> {
+        ron::ser::to_string_pretty(self, default()).map_err(BindingsError::Serialize)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, BindingsError> {
+        let contents = fs::read_to_string(path).map_err(|source| BindingsError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Self::from_ron(path, &contents)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), BindingsError> {
+        let contents = self.to_ron()?;
+        let io_err = |source| BindingsError::Io {
+            path: path.to_path_buf(),
+            source,
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(io_err)?;
+        }
+        fs::write(path, contents).map_err(io_err)
+    }
+
+    pub fn validate(&self) -> Result<(), BindingsError> {
+        let mut players = HashSet::new();
+        for player_bindings in self.players.iter() {
+            let player = player_bindings.player;
+            if !players.insert(player) {
+                return Err(BindingsError::DuplicatePlayer(player));
+            }
+
+            let mut axes = HashSet::new();
+            for binding in player_bindings.axes.iter() {
+                if !axes.insert(binding.axis) {
+                    return Err(BindingsError::DuplicateAxis(player, binding.axis));
+                }
+                if !(0.0..1.0).contains(&binding.deadzone) {
+                    return Err(BindingsError::InvalidDeadzone(
+                        player,
+                        binding.axis,
+                        binding.deadzone,
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        KeyBindings {
+            players: vec![
+                default_player_bindings(PlayerId::One, KeyCode::W, KeyCode::S),
+                default_player_bindings(PlayerId::Two, KeyCode::Up, KeyCode::Down),
+            ],
+        }
+    }
+}
+
+fn default_player_bindings(player: PlayerId, up: KeyCode, down: KeyCode) -> PlayerBindings {
+    PlayerBindings {
+        player,
+        axes: vec![AxisBinding {
+            axis: Axis::Vertical,
+            positive_keys: vec![up],
+            negative_keys: vec![down],
+            gamepad_axes: vec![GamepadAxisType::LeftStickY, GamepadAxisType::DPadY],
+            positive_gamepad_buttons: vec![GamepadButtonType::DPadUp],
+            negative_gamepad_buttons: vec![GamepadButtonType::DPadDown],
+            deadzone: DEFAULT_DEADZONE,
+        }],
+    }
+}
+
+impl From<&AxisBinding> for InputAxis {
+    fn from(binding: &AxisBinding) -> Self {
+        InputAxis {
+            axis_id: binding.axis,
+            positive_key_codes: binding.positive_keys.iter().copied().collect(),
+            negative_key_codes: binding.negative_keys.iter().copied().collect(),
+            gamepad_axes: binding.gamepad_axes.iter().copied().collect(),
+            positive_gamepad_buttons: binding.positive_gamepad_buttons.iter().copied().collect(),
+            negative_gamepad_buttons: binding.negative_gamepad_buttons.iter().copied().collect(),
+            deadzone: binding.deadzone,
+            ..default()
+        }
+    }
+}
+
+impl From<&InputAxis> for AxisBinding {
+    fn from(axis: &InputAxis) -> Self {
+        // sets have no order, sort so saved files stay stable between runs
+        let sorted_keys = |keys: &HashSet<KeyCode>| {
+            let mut keys: Vec<_> = keys.iter().copied().collect();
+            keys.sort();
+            keys
+        };
+        fn sorted_by_name<T: Copy + fmt::Debug>(values: &HashSet<T>) -> Vec<T> {
+            let mut values: Vec<_> = values.iter().copied().collect();
+            values.sort_by_key(|x| format!("{:?}", x));
+            values
+        }
+
+        AxisBinding {
+            axis: axis.axis_id,
+            positive_keys: sorted_keys(&axis.positive_key_codes),
+            negative_keys: sorted_keys(&axis.negative_key_codes),
+            gamepad_axes: sorted_by_name(&axis.gamepad_axes),
+            positive_gamepad_buttons: sorted_by_name(&axis.positive_gamepad_buttons),
+            negative_gamepad_buttons: sorted_by_name(&axis.negative_gamepad_buttons),
+            deadzone: axis.deadzone,
+        }
+    }
+}
+
+impl From<&PlayerBindings> for InputAxes {
+    fn from(bindings: &PlayerBindings) -> Self {
+        InputAxes {
+            player: bindings.player,
+            val: bindings
+                .axes
+                .iter()
+                .map(|x| (x.axis, InputAxis::from(x)))
+                .collect::<HashMap<_, _>>(),
+            ..default()
+        }
+    }
+}
+
+impl From<&InputAxes> for PlayerBindings {
+    fn from(axes: &InputAxes) -> Self {
+        let mut bindings: Vec<_> = axes.val.values().map(AxisBinding::from).collect();
+        bindings.sort_by_key(|x| x.axis as i32);
+        PlayerBindings {
+            player: axes.player,
+            axes: bindings,
+        }
+    }
+}
+
+pub fn load_bindings(config: &BindingsConfig) -> KeyBindings {
+    let path = match &config.path {
+        Some(path) if path.exists() => path,
+        _ => return KeyBindings::default(),
+    };
+
+    match KeyBindings::load(path) {
+        Ok(bindings) => bindings,
+        Err(err) => {
+            error!("{}; falling back to default key bindings", err);
+            KeyBindings::default()
+        }
+    }
+}
+
+pub fn save_bindings(
+    mut save_ev: EventReader<SaveBindings>,
+    config: Res<BindingsConfig>,
+    input_axes: Query<&InputAxes>,
+) {
+    if save_ev.iter().count() == 0 {
+        return;
+    }
+
+    let path = match &config.path {
+        Some(path) => path,
+        None => return,
+    };
+
+    let mut players: Vec<_> = input_axes.iter().map(PlayerBindings::from).collect();
+    players.sort_by_key(|x| x.player as i32);
+    if let Err(err) = (KeyBindings { players }).save(path) {
+        error!("{}", err);
+    }
+}
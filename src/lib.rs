@@ -0,0 +1,23 @@
+// bevy system parameters are naturally verbose
+#![allow(clippy::type_complexity)]
+
+pub mod ai;
+pub mod audio;
+pub mod config;
+pub mod game;
+pub mod game_entities;
+pub mod game_setup_systems;
+pub mod game_systems;
+pub mod game_ui_setup_systems;
+pub mod game_ui_systems;
+pub mod headless;
+pub mod input;
+pub mod input_bindings;
+pub mod net;
+pub mod powerups;
+pub mod replay;
+pub mod rng;
+pub mod server;
+pub mod tick;
+pub mod trajectory;
+pub mod utils;
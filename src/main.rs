@@ -1,31 +1,95 @@
-use bevy::{prelude::*, window::PresentMode};
-mod game;
-mod game_systems;
-mod game_entities;
-mod game_setup_systems;
-mod game_ui_systems;
-mod game_ui_setup_systems;
-mod input;
-mod utils;
+use bevy::{asset::AssetServerSettings, prelude::*, window::PresentMode};
+use bevy_pong::{
+    audio, game,
+    input::{self, PlayerId},
+    net::NetSession,
+    replay::{Replay, ReplayPlayer, ReplayRecorder},
+};
 use bevy_rapier2d::prelude::*;
+use std::{env, fmt::Display, net::SocketAddr, path::PathBuf, process};
 
 fn main() {
-    App::new()
-        .insert_resource(WindowDescriptor {
-            title: "Pong!".to_string(),
-            width: 1920.0,
-            height: 1080.0,
-            present_mode: PresentMode::Immediate,
-            ..default()
-        })
-        .add_plugins(DefaultPlugins)
-        .add_plugin(RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(100.0))
-        .add_plugin(RapierDebugRenderPlugin::default())
-        .add_plugin(bevy_framepace::FramepacePlugin {
-            framerate_limit: bevy_framepace::FramerateLimit::Manual(144),
-            warn_on_frame_drop: false,
-        })
-        .add_plugin(input::PongInput)
-        .add_plugin(game::PongGame)
-        .run();
+    let mut app = App::new();
+    // `--record <file>` saves a replay of every match, `--replay <file>` plays one back and
+    // `--netplay <1|2> <bind addr> <peer addr>` plays against a peer over UDP
+    let mut args = env::args().skip(1);
+    while let Some(flag) = args.next() {
+        match (flag.as_str(), args.next()) {
+            ("--record", Some(path)) => {
+                app.insert_resource(ReplayRecorder {
+                    path: Some(PathBuf::from(path)),
+                    ..default()
+                });
+            }
+            ("--replay", Some(path)) => match Replay::load(path.as_ref()) {
+                Ok(replay) => {
+                    app.insert_resource(ReplayPlayer::new(replay));
+                }
+                Err(e) => exit_with_error(e),
+            },
+            ("--netplay", Some(player)) => {
+                let player = match player.as_str() {
+                    "1" => PlayerId::One,
+                    "2" => PlayerId::Two,
+                    _ => exit_with_usage(),
+                };
+                let (local, peer) = match (parse_addr(args.next()), parse_addr(args.next())) {
+                    (Some(local), Some(peer)) => (local, peer),
+                    _ => exit_with_usage(),
+                };
+                match NetSession::bind(local, peer, player, rand::random()) {
+                    Ok(session) => {
+                        app.insert_resource(session);
+                    }
+                    Err(e) => exit_with_error(e),
+                }
+            }
+            _ => exit_with_usage(),
+        }
+    }
+
+    app.insert_resource(WindowDescriptor {
+        title: "Pong!".to_string(),
+        width: 1920.0,
+        height: 1080.0,
+        present_mode: PresentMode::Immediate,
+        ..default()
+    })
+    // picks up edits to the game config while running
+    .insert_resource(AssetServerSettings {
+        watch_for_changes: true,
+        ..default()
+    })
+    .add_plugins(DefaultPlugins)
+    // stepped by the game's fixed tick instead
+    .add_plugin(
+        RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(game::PIXELS_PER_METER)
+            .with_default_system_setup(false),
+    )
+    .add_plugin(RapierDebugRenderPlugin::default())
+    .add_plugin(bevy_framepace::FramepacePlugin {
+        framerate_limit: bevy_framepace::FramerateLimit::Manual(144),
+        warn_on_frame_drop: false,
+    })
+    .add_plugin(input::PongInput)
+    .add_plugin(game::PongGame)
+    .add_plugin(audio::PongAudio)
+    .run();
+}
+
+fn parse_addr(arg: Option<String>) -> Option<SocketAddr> {
+    arg?.parse().ok()
+}
+
+fn exit_with_error(e: impl Display) -> ! {
+    eprintln!("{}", e);
+    process::exit(1);
+}
+
+fn exit_with_usage() -> ! {
+    eprintln!(
+        "usage: bevy_pong [--record <file>] [--replay <file>] \
+         [--netplay <1|2> <bind addr> <peer addr>]"
+    );
+    process::exit(2);
 }
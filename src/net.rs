@@ -0,0 +1,740 @@
+use super::{
+    config::PongConfigSource,
+    game::Label,
+    game_entities::*,
+    input::{self, Axis, InputAxes, PlayerId},
+    powerups::PowerUpSpawner,
+    rng::GameRng,
+    tick::{Resimulate, TickAppExt, TickHold, TickStage},
+    utils::ByteReader,
+};
+use bevy::{ecs::event::Events, prelude::*};
+use bevy_rapier2d::{
+    plugin::systems::writeback_rigid_bodies,
+    prelude::*,
+    rapier::prelude::{
+        BroadPhase, CCDSolver, ColliderSet, ImpulseJointSet, IslandManager, MultibodyJointSet,
+        NarrowPhase, QueryPipeline, RigidBodySet,
+    },
+};
+use iyes_loopless::prelude::*;
+use rand::RngCore;
+use std::{
+    collections::{BTreeMap, VecDeque},
+    hash::Hasher,
+    io,
+    net::{SocketAddr, UdpSocket},
+};
+
+const PROTOCOL: &[u8; 3] = b"PN1";
+const MAX_PACKET_SIZE: usize = 1024;
+const MAX_INPUTS_PER_PACKET: usize = 64;
+const CHECKSUMS_PER_PACKET: usize = 8;
+
+#[derive(Debug, Clone)]
+pub struct NetConfig {
+    /// Ticks between reading local input and applying it. Hides that much latency without
+    /// any rollback, at the cost of controls feeling that much later.
+    pub input_delay: u32,
+    /// Ticks the simulation may run ahead of the peer's last known input before it waits.
+    pub max_prediction: u32,
+}
+
+impl Default for NetConfig {
+    fn default() -> Self {
+        NetConfig {
+            input_delay: 2,
+            max_prediction: 8,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NetStats {
+    pub rollbacks: u32,
+    pub resimulated_ticks: u32,
+    /// Ticks both peers compared their state on and agreed.
+    pub verified_ticks: u32,
+    pub desyncs: u32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Packet {
+    /// Sent until the match starts. `ack` tells the peer its hellos are arriving, and player
+    /// one's seed is the one both peers play with.
+    Hello { ack: bool, seed: u64 },
+    /// Local inputs from tick `start` on, how many of the peer's inputs have arrived and
+    /// checksums of recent confirmed ticks.
+    Inputs {
+        ack: u32,
+        start: u32,
+        inputs: Vec<f32>,
+        checksums: Vec<(u32, u64)>,
+    },
+}
+
+impl Packet {
+    fn encode(&self) -> Vec<u8> {
+        let mut bytes = PROTOCOL.to_vec();
+        match self {
+            Packet::Hello { ack, seed } => {
+                bytes.push(0);
+                bytes.push(*ack as u8);
+                bytes.extend_from_slice(&seed.to_le_bytes());
+            }
+            Packet::Inputs {
+                ack,
+                start,
+                inputs,
+                checksums,
+            } => {
+                bytes.push(1);
+                bytes.extend_from_slice(&ack.to_le_bytes());
+                bytes.extend_from_slice(&start.to_le_bytes());
+                bytes.push(inputs.len() as u8);
+                for input in inputs {
+                    bytes.extend_from_slice(&input.to_le_bytes());
+                }
+                bytes.push(checksums.len() as u8);
+                for (tick, checksum) in checksums {
+                    bytes.extend_from_slice(&tick.to_le_bytes());
+                    bytes.extend_from_slice(&checksum.to_le_bytes());
+                }
+            }
+        }
+        bytes
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Packet> {
+        let mut reader = ByteReader(bytes);
+        if reader.take(PROTOCOL.len())? != PROTOCOL {
+            return None;
+        }
+        let packet = match reader.u8()? {
+            0 => Packet::Hello {
+                ack: reader.u8()? != 0,
+                seed: reader.u64()?,
+            },
+            1 => {
+                let ack = reader.u32()?;
+                let start = reader.u32()?;
+                // inputs go straight into the simulation, where a NaN would stick for good
+                let inputs = (0..reader.u8()?)
+                    .map(|_| {
+                        let input = reader.f32()?;
+                        input.is_finite().then(|| input.clamp(-1.0, 1.0))
+                    })
+                    .collect::<Option<_>>()?;
+                let checksums = (0..reader.u8()?)
+                    .map(|_| Some((reader.u32()?, reader.u64()?)))
+                    .collect::<Option<_>>()?;
+                Packet::Inputs {
+                    ack,
+                    start,
+                    inputs,
+                    checksums,
+                }
+            }
+            _ => return None,
+        };
+        reader.is_empty().then_some(packet)
+    }
+}
+
+/// Rapier's state, everything but what's rebuilt on every step.
+struct PhysicsSnapshot {
+    islands: IslandManager,
+    broad_phase: BroadPhase,
+    narrow_phase: NarrowPhase,
+    bodies: RigidBodySet,
+    colliders: ColliderSet,
+    impulse_joints: ImpulseJointSet,
+    multibody_joints: MultibodyJointSet,
+    ccd_solver: CCDSolver,
+    query_pipeline: QueryPipeline,
+}
+
+impl PhysicsSnapshot {
+    fn save(context: &RapierContext) -> Self {
+        PhysicsSnapshot {
+            islands: context.islands.clone(),
+            broad_phase: context.broad_phase.clone(),
+            narrow_phase: context.narrow_phase.clone(),
+            bodies: context.bodies.clone(),
+            colliders: context.colliders.clone(),
+            impulse_joints: context.impulse_joints.clone(),
+            multibody_joints: context.multibody_joints.clone(),
+            ccd_solver: context.ccd_solver.clone(),
+            query_pipeline: context.query_pipeline.clone(),
+        }
+    }
+
+    fn restore(&self, context: &mut RapierContext) {
+        context.islands = self.islands.clone();
+        context.broad_phase = self.broad_phase.clone();
+        context.narrow_phase = self.narrow_phase.clone();
+        context.bodies = self.bodies.clone();
+        context.colliders = self.colliders.clone();
+        context.impulse_joints = self.impulse_joints.clone();
+        context.multibody_joints = self.multibody_joints.clone();
+        context.ccd_solver = self.ccd_solver.clone();
+        context.query_pipeline = self.query_pipeline.clone();
+    }
+}
+
+/// 64-bit FNV-1a, so both peers, whatever they were built with, checksum alike.
+struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Fnv1a(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for Fnv1a {
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// The match as it was at the start of `tick`. Transforms and velocities of balls and
+/// paddles live in the physics snapshot and are written back from there.
+struct Snapshot {
+    tick: u32,
+    checksum: u64,
+    clock: GameClock,
+    rng: GameRng,
+    contacts: StartedContacts,
+    scores: Vec<(Entity, MatchScore)>,
+    balls: Vec<(Entity, Ball, BallLaunchTimer, LastHitBy)>,
+    paddles: Vec<(Entity, PaddleMotion)>,
+    physics: PhysicsSnapshot,
+}
+
+impl Snapshot {
+    fn save(world: &mut World, tick: u32) -> Self {
+        let scores = world
+            .query::<(Entity, &MatchScore)>()
+            .iter(world)
+            .map(|(entity, score)| (entity, score.clone()))
+            .collect();
+        let balls = world
+            .query::<(Entity, &Ball, &BallLaunchTimer, &LastHitBy)>()
+            .iter(world)
+            .map(|(entity, ball, timer, last_hit)| {
+                (entity, ball.clone(), timer.clone(), last_hit.clone())
+            })
+            .collect();
+        let paddles = world
+            .query::<(Entity, &PaddleMotion)>()
+            .iter(world)
+            .map(|(entity, motion)| (entity, motion.clone()))
+            .collect();
+        let mut snapshot = Snapshot {
+            tick,
+            checksum: 0,
+            clock: world.resource::<GameClock>().clone(),
+            rng: world.resource::<GameRng>().clone(),
+            contacts: world.resource::<StartedContacts>().clone(),
+            scores,
+            balls,
+            paddles,
+            physics: PhysicsSnapshot::save(world.resource::<RapierContext>()),
+        };
+        snapshot.checksum = snapshot.compute_checksum(world);
+        snapshot
+    }
+
+    fn compute_checksum(&self, world: &mut World) -> u64 {
+        let mut hasher = Fnv1a::default();
+        let mut bodies: Vec<_> = world
+            .query_filtered::<(Entity, &Transform, &Velocity), Or<(With<Ball>, With<Paddle>)>>()
+            .iter(world)
+            .map(|(entity, transform, velocity)| (entity, transform.translation, *velocity))
+            .collect();
+        bodies.sort_by_key(|x| x.0);
+        for (_, translation, velocity) in bodies {
+            let linvel = velocity.linvel;
+            for val in [
+                translation.x,
+                translation.y,
+                linvel.x,
+                linvel.y,
+                velocity.angvel,
+            ] {
+                hasher.write(&val.to_bits().to_le_bytes());
+            }
+        }
+        for (_, score) in self.scores.iter() {
+            for team in [Team::Player, Team::AI] {
+                let goals = score.score.get(&team).copied().unwrap_or_default();
+                hasher.write(&goals.to_le_bytes());
+            }
+        }
+        hasher.write(&self.rng.clone().next_u64().to_le_bytes());
+        hasher.finish()
+    }
+
+    fn restore(&self, world: &mut World) {
+        world.insert_resource(self.clock.clone());
+        world.insert_resource(self.rng.clone());
+        world.insert_resource(self.contacts.clone());
+        for (entity, score) in self.scores.iter() {
+            world.entity_mut(*entity).insert(score.clone());
+        }
+        for (entity, ball, timer, last_hit) in self.balls.iter() {
+            world
+                .entity_mut(*entity)
+                .insert(ball.clone())
+                .insert(timer.clone())
+                .insert(last_hit.clone());
+        }
+        for (entity, motion) in self.paddles.iter() {
+            world.entity_mut(*entity).insert(motion.clone());
+        }
+        // launches still queued are for the discarded ticks, `reset_ball` has already
+        // reset the timer of any restored ball that's waiting
+        world.resource_mut::<Events<BallLaunch>>().clear();
+
+        self.physics
+            .restore(&mut world.resource_mut::<RapierContext>());
+        // puts transforms and velocities back and lets Rapier know they match its bodies
+        SystemStage::single_threaded()
+            .with_system(writeback_rigid_bodies)
+            .run(world);
+    }
+}
+
+/// Plays a two player match against a peer over UDP while present. Each peer reads its own
+/// player one controls and simulates ahead with the peer's last known input, rolling back and
+/// resimulating when the real input turns out different. Both peers use the built-in config
+/// and no power-ups, and periodically compare checksums of confirmed ticks to catch desyncs.
+///
+/// The match starts once both peers have found each other and ends on a confirmed victory.
+pub struct NetSession {
+    socket: UdpSocket,
+    peer: SocketAddr,
+    local_player: PlayerId,
+    config: NetConfig,
+    seed: u64,
+    seed_known: bool,
+    peer_heard_us: bool,
+    started: bool,
+    finished: bool,
+    start_tick: u64,
+    /// Player one's controls as read this frame, before any tick overwrites them.
+    live_input: f32,
+    local_inputs: Vec<f32>,
+    remote_inputs: Vec<f32>,
+    /// Remote input each simulated tick ran with, predicted or not.
+    remote_used: Vec<f32>,
+    mispredicted: Option<u32>,
+    peer_ack: u32,
+    snapshots: VecDeque<Snapshot>,
+    confirmed_until: u32,
+    local_checksums: BTreeMap<u32, u64>,
+    remote_checksums: BTreeMap<u32, u64>,
+    outgoing_checksums: VecDeque<(u32, u64)>,
+    stats: NetStats,
+}
+
+impl NetSession {
+    /// Player one's `seed` decides the match, player two's is ignored.
+    pub fn new(
+        socket: UdpSocket,
+        peer: SocketAddr,
+        local_player: PlayerId,
+        seed: u64,
+    ) -> io::Result<Self> {
+        socket.set_nonblocking(true)?;
+        Ok(NetSession {
+            socket,
+            peer,
+            local_player,
+            config: default(),
+            seed,
+            seed_known: local_player == PlayerId::One,
+            peer_heard_us: false,
+            started: false,
+            finished: false,
+            start_tick: 0,
+            live_input: 0.0,
+            local_inputs: Vec::new(),
+            remote_inputs: Vec::new(),
+            remote_used: Vec::new(),
+            mispredicted: None,
+            peer_ack: 0,
+            snapshots: VecDeque::new(),
+            confirmed_until: 0,
+            local_checksums: BTreeMap::new(),
+            remote_checksums: BTreeMap::new(),
+            outgoing_checksums: VecDeque::new(),
+            stats: default(),
+        })
+    }
+
+    pub fn bind(
+        local: SocketAddr,
+        peer: SocketAddr,
+        local_player: PlayerId,
+        seed: u64,
+    ) -> io::Result<Self> {
+        Self::new(UdpSocket::bind(local)?, peer, local_player, seed)
+    }
+
+    pub fn with_config(mut self, config: NetConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn local_player(&self) -> PlayerId {
+        self.local_player
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.started && !self.finished
+    }
+
+    pub fn stats(&self) -> NetStats {
+        self.stats
+    }
+
+    fn receive(&mut self) {
+        let mut buf = [0; MAX_PACKET_SIZE];
+        loop {
+            match self.socket.recv_from(&mut buf) {
+                Ok((len, from)) if from == self.peer => {
+                    if let Some(packet) = Packet::decode(&buf[..len]) {
+                        self.handle_packet(packet);
+                    }
+                }
+                Ok(_) => (),
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                // a previous send to a peer that isn't listening yet
+                Err(e) if e.kind() == io::ErrorKind::ConnectionReset => (),
+                Err(e) => {
+                    warn!("netplay receive failed: {}", e);
+                    break;
+                }
+            }
+        }
+    }
+
+    fn handle_packet(&mut self, packet: Packet) {
+        match packet {
+            Packet::Hello { ack, seed } => {
+                if self.local_player != PlayerId::One && !self.started {
+                    self.seed = seed;
+                    self.seed_known = true;
+                }
+                self.peer_heard_us |= ack;
+            }
+            Packet::Inputs {
+                ack,
+                start,
+                inputs,
+                checksums,
+            } => {
+                self.peer_heard_us = true;
+                self.peer_ack = self.peer_ack.max(ack);
+                for (tick, input) in (start..).zip(inputs) {
+                    let next = self.remote_inputs.len() as u32;
+                    if tick < next {
+                        continue;
+                    } else if tick > next {
+                        break;
+                    }
+                    self.remote_inputs.push(input);
+                    let used = self.remote_used.get(tick as usize);
+                    if used.is_some_and(|x| *x != input) {
+                        self.mispredicted = Some(self.mispredicted.map_or(tick, |x| x.min(tick)));
+                    }
+                }
+                for (tick, checksum) in checksums {
+                    if tick >= self.confirmed_until {
+                        self.remote_checksums.insert(tick, checksum);
+                    } else if let Some(local) = self.local_checksums.remove(&tick) {
+                        self.compare_checksums(tick, local, checksum);
+                    }
+                }
+            }
+        }
+    }
+
+    fn compare_checksums(&mut self, tick: u32, local: u64, remote: u64) {
+        if local == remote {
+            self.stats.verified_ticks += 1;
+        } else {
+            self.stats.desyncs += 1;
+            error!("netplay desync at tick {}", tick);
+        }
+    }
+
+    /// Marks the snapshots up to `limit` as final, checksums them and ends the match if one
+    /// of them has a winner.
+    fn confirm_snapshots(&mut self, world: &mut World, limit: u32) {
+        let win_condition = world.resource::<WinCondition>().clone();
+        while self.confirmed_until <= limit {
+            let tick = self.confirmed_until;
+            let snapshot = match self.snapshots.iter().find(|x| x.tick == tick) {
+                Some(snapshot) => snapshot,
+                None => break,
+            };
+            let checksum = snapshot.checksum;
+            let winner = snapshot
+                .scores
+                .iter()
+                .find_map(|(_, score)| win_condition.winner(score));
+            if let Some(team) = winner {
+                // end on the confirmed state, whatever was predicted past it
+                snapshot.restore(world);
+                world.remove_resource::<Resimulate>();
+                world
+                    .resource_mut::<Events<MatchWon>>()
+                    .send(MatchWon { team });
+                world.insert_resource(NextState(GameState::GameOver));
+                self.finished = true;
+            }
+
+            self.confirmed_until += 1;
+            self.outgoing_checksums.push_back((tick, checksum));
+            if self.outgoing_checksums.len() > CHECKSUMS_PER_PACKET {
+                self.outgoing_checksums.pop_front();
+            }
+            match self.remote_checksums.remove(&tick) {
+                Some(remote) => self.compare_checksums(tick, checksum, remote),
+                None => {
+                    self.local_checksums.insert(tick, checksum);
+                }
+            }
+            if self.finished {
+                break;
+            }
+        }
+
+        let confirmed_until = self.confirmed_until;
+        self.snapshots.retain(|x| x.tick + 1 >= confirmed_until);
+        // checksums the peer never sent, e.g. lost with their packets
+        let oldest = confirmed_until.saturating_sub(CHECKSUMS_PER_PACKET as u32 * 4);
+        self.local_checksums.retain(|tick, _| *tick >= oldest);
+        self.remote_checksums.retain(|tick, _| *tick >= oldest);
+    }
+
+    fn next_packet(&self) -> Packet {
+        if !self.started {
+            return Packet::Hello {
+                ack: self.seed_known,
+                seed: self.seed,
+            };
+        }
+        let start = (self.peer_ack as usize).min(self.local_inputs.len());
+        Packet::Inputs {
+            ack: self.remote_inputs.len() as u32,
+            start: start as u32,
+            inputs: self.local_inputs[start..]
+                .iter()
+                .take(MAX_INPUTS_PER_PACKET)
+                .copied()
+                .collect(),
+            checksums: self.outgoing_checksums.iter().copied().collect(),
+        }
+    }
+}
+
+pub struct PongNet;
+impl Plugin for PongNet {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system_to_stage(StartupStage::PreStartup, prepare_net_match)
+            .add_exit_system(GameState::MainMenu, start_net_match.label(Label::NetInput))
+            .add_system_to_stage(CoreStage::Update, update_net_session.exclusive_system())
+            .add_system(
+                read_local_input
+                    .run_if_resource_exists::<NetSession>()
+                    .after(input::Label::Default),
+            )
+            .add_system_to_stage(
+                CoreStage::Last,
+                send_net_packets.run_if_resource_exists::<NetSession>(),
+            )
+            .add_tick_system(
+                TickStage::Gameplay,
+                save_net_snapshot.exclusive_system().at_start(),
+            )
+            .add_tick_system_set(
+                TickStage::Gameplay,
+                ConditionSet::new()
+                    .run_in_state(GameState::Playing)
+                    .run_if_resource_exists::<NetSession>()
+                    .label(Label::NetInput)
+                    .after(Label::Clock)
+                    .before(Label::CollisionCheck)
+                    .with_system(apply_net_inputs)
+                    .into(),
+            );
+    }
+}
+
+/// Holds the game in the main menu until the peer is found and takes out everything the
+/// peers can't agree on.
+pub fn prepare_net_match(
+    mut commands: Commands,
+    session: Option<Res<NetSession>>,
+    mut physics: ResMut<RapierConfiguration>,
+) {
+    if session.is_none() {
+        return;
+    }
+    commands.remove_resource::<NextState<GameState>>();
+    commands.insert_resource(MatchMode::TwoPlayer);
+    commands.insert_resource(PongConfigSource { path: None });
+    commands.insert_resource(PowerUpSpawner {
+        max_active: 0,
+        ..default()
+    });
+    // nothing may move before both peers start
+    physics.physics_pipeline_active = false;
+}
+
+pub fn start_net_match(
+    session: Option<ResMut<NetSession>>,
+    clock: Res<GameClock>,
+    mut rng: ResMut<GameRng>,
+) {
+    let mut session = match session {
+        Some(session) if session.started => session,
+        _ => return,
+    };
+    session.start_tick = clock.tick;
+    session.local_inputs = vec![0.0; session.config.input_delay as usize];
+    rng.reseed(session.seed);
+}
+
+/// Takes in the peer's packets and, once the match runs, rolls back to the first tick that
+/// was predicted wrong.
+pub fn update_net_session(world: &mut World) {
+    if !world.contains_resource::<NetSession>() {
+        return;
+    }
+    world.resource_scope(|world, mut session: Mut<NetSession>| {
+        session.receive();
+        if !session.started {
+            if session.seed_known && session.peer_heard_us {
+                info!("netplay peer found, starting the match");
+                session.started = true;
+                world.insert_resource(NextState(GameState::Playing));
+            }
+            return;
+        }
+        if session.finished || world.resource::<CurrentState<GameState>>().0 != GameState::Playing {
+            world.remove_resource::<TickHold>();
+            return;
+        }
+
+        let simulated = session.remote_used.len() as u32;
+        let rollback = session.mispredicted.take();
+        if let Some(tick) = rollback {
+            match session.snapshots.iter().find(|x| x.tick == tick) {
+                Some(snapshot) => snapshot.restore(world),
+                None => {
+                    error!("netplay can't roll back to tick {}", tick);
+                    return;
+                }
+            }
+            session.remote_used.truncate(tick as usize);
+            world.insert_resource(Resimulate(simulated - tick));
+            session.stats.rollbacks += 1;
+            session.stats.resimulated_ticks += simulated - tick;
+        }
+
+        let confirmed = session.remote_inputs.len() as u32;
+        let limit = confirmed
+            .min(rollback.unwrap_or(u32::MAX))
+            .min(simulated.saturating_sub(1));
+        session.confirm_snapshots(world, limit);
+
+        if simulated.saturating_sub(confirmed) >= session.config.max_prediction {
+            world.insert_resource(TickHold);
+        } else {
+            world.remove_resource::<TickHold>();
+        }
+    });
+}
+
+pub fn send_net_packets(session: Res<NetSession>) {
+    let bytes = session.next_packet().encode();
+    if let Err(e) = session.socket.send_to(&bytes, session.peer) {
+        if e.kind() != io::ErrorKind::WouldBlock {
+            debug!("netplay send failed: {}", e);
+        }
+    }
+}
+
+pub fn save_net_snapshot(world: &mut World) {
+    if world.resource::<CurrentState<GameState>>().0 != GameState::Playing {
+        return;
+    }
+    let tick = match world.get_resource::<NetSession>() {
+        Some(session) if session.is_running() => {
+            (world.resource::<GameClock>().tick - session.start_tick) as u32
+        }
+        _ => return,
+    };
+    let snapshot = Snapshot::save(world, tick);
+    let mut session = world.resource_mut::<NetSession>();
+    session.snapshots.retain(|x| x.tick < tick);
+    session.snapshots.push_back(snapshot);
+}
+
+/// Reads the local controls once a frame, as the ticks that follow write both players' inputs
+/// over player one's axes.
+pub fn read_local_input(mut session: ResMut<NetSession>, input_axes: Query<&InputAxes>) {
+    session.live_input = input_axes
+        .iter()
+        .find(|x| x.player == PlayerId::One)
+        .and_then(|x| x.val.get(&Axis::Vertical))
+        .map_or(0.0, |x| x.val);
+}
+
+/// Applies both players' input for this tick: the local one delayed by `input_delay`, the
+/// remote one as far as it's known and predicted from the last known one past that.
+pub fn apply_net_inputs(
+    clock: Res<GameClock>,
+    mut session: ResMut<NetSession>,
+    mut input_axes: Query<&mut InputAxes>,
+) {
+    if !session.is_running() {
+        return;
+    }
+    let tick = (clock.tick - session.start_tick - 1) as usize;
+    // read once per tick, resimulated ticks keep what was read the first time around
+    if session.local_inputs.len() == tick + session.config.input_delay as usize {
+        let live = session.live_input;
+        session.local_inputs.push(live);
+    }
+
+    let local = session.local_inputs[tick];
+    let remote = session
+        .remote_inputs
+        .get(tick)
+        .or_else(|| session.remote_inputs.last())
+        .copied()
+        .unwrap_or_default();
+    session.remote_used.push(remote);
+    for mut axes in input_axes.iter_mut() {
+        let val = if axes.player == session.local_player {
+            local
+        } else {
+            remote
+        };
+        if let Some(axis) = axes.val.get_mut(&Axis::Vertical) {
+            axis.val = val;
+        }
+    }
+}
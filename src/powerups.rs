@@ -0,0 +1,529 @@
+use super::{
+    config::PongConfig,
+    game::Label,
+    game_entities::*,
+    game_setup_systems::spawn_launched_ball,
+    game_systems::apply_config,
+    rng::GameRng,
+    tick::{TickAppExt, TickStage},
+    trajectory::ArenaBounds,
+    utils::{lerp, rotate_vec2},
+};
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use iyes_loopless::prelude::*;
+use rand::Rng;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PowerUpKind {
+    BiggerPaddle,
+    SmallerPaddle,
+    FasterPaddle,
+    MultiBall,
+    SlowBall,
+    ReversedControls,
+}
+
+/// Effects sharing a slot overwrite each other on the same target instead of stacking,
+/// so expiring one always restores the value from before any of them was applied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EffectSlot {
+    PaddleSize,
+    PaddleSpeed,
+    Controls,
+    BallSpeed,
+    Instant,
+}
+
+impl PowerUpKind {
+    pub const ALL: [PowerUpKind; 6] = [
+        PowerUpKind::BiggerPaddle,
+        PowerUpKind::SmallerPaddle,
+        PowerUpKind::FasterPaddle,
+        PowerUpKind::MultiBall,
+        PowerUpKind::SlowBall,
+        PowerUpKind::ReversedControls,
+    ];
+
+    pub fn random(rng: &mut impl Rng) -> Self {
+        Self::ALL[rng.gen_range(0..Self::ALL.len())]
+    }
+
+    pub fn duration(&self) -> f32 {
+        match self {
+            PowerUpKind::MultiBall => 0.0,
+            PowerUpKind::ReversedControls => 5.0,
+            _ => 8.0,
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            PowerUpKind::BiggerPaddle => Color::GREEN,
+            PowerUpKind::SmallerPaddle => Color::RED,
+            PowerUpKind::FasterPaddle => Color::YELLOW,
+            PowerUpKind::MultiBall => Color::CYAN,
+            PowerUpKind::SlowBall => Color::BLUE,
+            PowerUpKind::ReversedControls => Color::PURPLE,
+        }
+    }
+
+    fn slot(&self) -> EffectSlot {
+        match self {
+            PowerUpKind::BiggerPaddle | PowerUpKind::SmallerPaddle => EffectSlot::PaddleSize,
+            PowerUpKind::FasterPaddle => EffectSlot::PaddleSpeed,
+            PowerUpKind::ReversedControls => EffectSlot::Controls,
+            PowerUpKind::SlowBall => EffectSlot::BallSpeed,
+            PowerUpKind::MultiBall => EffectSlot::Instant,
+        }
+    }
+}
+
+const SLOW_BALL_SCALE: f32 = 0.5;
+
+#[derive(Clone, Component)]
+pub struct PowerUp {
+    pub kind: PowerUpKind,
+    pub radius: f32,
+}
+
+/// Time left on a timed effect; the effect is undone once it finishes.
+#[derive(Clone, Component)]
+pub struct EffectDuration(pub Timer);
+
+/// Value an effect replaced, put back when the effect expires.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OriginalValue {
+    PaddleSize(Vec2),
+    PaddleSpeed(f32),
+    Controls,
+    /// Undone by speeding the ball up again, keeping what paddle hits added meanwhile.
+    BallSpeed,
+}
+
+/// A timed effect acting on `target`, living on its own entity next to an `EffectDuration`.
+#[derive(Clone, Component)]
+pub struct ActiveEffect {
+    pub kind: PowerUpKind,
+    pub target: Entity,
+    pub original: OriginalValue,
+}
+
+/// The `ActiveEffect` entities acting on a ball or paddle, so a target's effects are found
+/// without going through everyone else's.
+#[derive(Clone, Component, Default)]
+pub struct ActiveEffects(pub Vec<Entity>);
+
+/// Swaps the direction of a paddle's vertical input.
+#[derive(Clone, Component, Default)]
+pub struct ReversedControls;
+
+/// Ball added by a multi-ball power-up, removed from play once it scores.
+#[derive(Clone, Component, Default)]
+pub struct ExtraBall;
+
+pub struct PowerUpCollected {
+    pub kind: PowerUpKind,
+    pub paddle: Entity,
+    pub ball: Entity,
+}
+
+pub struct PowerUpSpawner {
+    pub timer: Timer,
+    pub max_active: usize,
+    pub radius: f32,
+}
+
+impl Default for PowerUpSpawner {
+    fn default() -> Self {
+        PowerUpSpawner {
+            timer: Timer::from_seconds(8.0, true),
+            max_active: 2,
+            radius: 20.0,
+        }
+    }
+}
+
+pub struct PongPowerUps;
+impl Plugin for PongPowerUps {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PowerUpSpawner>()
+            .add_event::<PowerUpCollected>()
+            .add_enter_system(GameState::MainMenu, clear_power_ups)
+            .add_exit_system(GameState::GameOver, clear_power_ups)
+            .add_system(reapply_paddle_effects.after(apply_config))
+            .add_tick_system_set(
+                TickStage::Gameplay,
+                ConditionSet::new()
+                    .run_in_state(GameState::Playing)
+                    .label(Label::PowerUps)
+                    .after(Label::CollisionCheck)
+                    .before(Label::Default)
+                    .with_system(spawn_power_ups)
+                    .with_system(
+                        collect_power_ups
+                            .into_conditional()
+                            .label(Label::PowerUpCollection),
+                    )
+                    .with_system(
+                        apply_power_ups
+                            .into_conditional()
+                            .after(Label::PowerUpCollection),
+                    )
+                    .with_system(expire_effects)
+                    .into(),
+            );
+    }
+}
+
+pub fn spawn_power_ups(
+    mut commands: Commands,
+    clock: Res<GameClock>,
+    arena: Res<ArenaBounds>,
+    mut spawner: ResMut<PowerUpSpawner>,
+    mut rng: ResMut<GameRng>,
+    query: Query<&PowerUp>,
+) {
+    spawner.timer.tick(clock.delta);
+    if !spawner.timer.just_finished() || query.iter().count() >= spawner.max_active {
+        return;
+    }
+
+    // keep away from the paddles so there's time to react to what's been collected
+    let x = lerp(arena.min.x, arena.max.x, lerp(0.3, 0.7, rng.gen::<f32>()));
+    let y = lerp(
+        arena.min.y + spawner.radius,
+        arena.max.y - spawner.radius,
+        rng.gen::<f32>(),
+    );
+    spawn_power_up(
+        &mut commands,
+        PowerUpKind::random(&mut *rng),
+        Vec2::new(x, y),
+        spawner.radius,
+    );
+}
+
+pub fn spawn_power_up(
+    commands: &mut Commands,
+    kind: PowerUpKind,
+    position: Vec2,
+    radius: f32,
+) -> Entity {
+    commands
+        .spawn_bundle(SpriteBundle {
+            sprite: Sprite {
+                color: kind.color(),
+                custom_size: Some(Vec2::splat(radius * 2.0)),
+                ..default()
+            },
+            transform: Transform::from_translation(position.extend(0.0)),
+            ..default()
+        })
+        .insert(PowerUp { kind, radius })
+        .id()
+}
+
+/// Hands power-ups touched by a ball to the paddle that hit it last. Balls nobody has
+/// hit yet pass over power-ups without collecting them.
+pub fn collect_power_ups(
+    mut commands: Commands,
+    mut collected_ev: EventWriter<PowerUpCollected>,
+    ball_query: Query<(Entity, &Transform, &Collider, &LastHitBy), With<Ball>>,
+    power_up_query: Query<(Entity, &Transform, &PowerUp)>,
+) {
+    for (p_entity, p_transform, power_up) in power_up_query.iter() {
+        let p_position = p_transform.translation.truncate();
+        let collector = ball_query
+            .iter()
+            .find_map(|(entity, transform, collider, last_hit)| {
+                let radius = collider.as_ball().map_or(0.0, |x| x.radius());
+                let distance = transform.translation.truncate().distance(p_position);
+                last_hit
+                    .0
+                    .filter(|_| distance <= radius + power_up.radius)
+                    .map(|paddle| (entity, paddle))
+            });
+
+        if let Some((ball, paddle)) = collector {
+            commands.entity(p_entity).despawn_recursive();
+            collected_ev.send(PowerUpCollected {
+                kind: power_up.kind,
+                paddle,
+                ball,
+            });
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn apply_power_ups(
+    mut commands: Commands,
+    mut collected_ev: EventReader<PowerUpCollected>,
+    assets: Res<AssetServer>,
+    config: Res<PongConfig>,
+    mut effect_query: Query<(&ActiveEffect, &mut EffectDuration)>,
+    mut target_query: Query<&mut ActiveEffects>,
+    mut paddle_query: Query<(&mut Paddle, &mut Sprite, &mut Collider), Without<Ball>>,
+    mut ball_query: Query<(&Transform, &mut Velocity), With<Ball>>,
+) {
+    for ev in collected_ev.iter() {
+        let target = match ev.kind.slot() {
+            EffectSlot::BallSpeed => ev.ball,
+            _ => ev.paddle,
+        };
+
+        if ev.kind.slot() == EffectSlot::Instant {
+            if let Ok((transform, velocity)) = ball_query.get(ev.ball) {
+                spawn_extra_ball(&mut commands, &assets, &config, transform, velocity);
+            }
+            continue;
+        }
+
+        let mut effects = match target_query.get_mut(target) {
+            Ok(effects) => effects,
+            Err(_) => continue,
+        };
+        // the same slot is already taken, replace that effect but keep its original value
+        let existing = effects.0.iter().position(|entity| {
+            matches!(effect_query.get(*entity), Ok((effect, _)) if effect.kind.slot() == ev.kind.slot())
+        });
+        if let Some(index) = existing {
+            let entity = effects.0[index];
+            let (effect, mut duration) = effect_query.get_mut(entity).unwrap();
+            if effect.kind == ev.kind {
+                duration.0.reset();
+                continue;
+            }
+            let original = effect.original;
+            commands.entity(entity).despawn();
+            effects.0.swap_remove(index);
+            apply_effect(
+                &mut commands,
+                ev.kind,
+                target,
+                original,
+                &mut effects,
+                &mut paddle_query,
+                &mut ball_query,
+            );
+            continue;
+        }
+
+        let original = match ev.kind.slot() {
+            EffectSlot::PaddleSize => paddle_query
+                .get(target)
+                .ok()
+                .and_then(|(_, sprite, _)| sprite.custom_size)
+                .map(OriginalValue::PaddleSize),
+            EffectSlot::PaddleSpeed => paddle_query
+                .get(target)
+                .ok()
+                .map(|(paddle, _, _)| OriginalValue::PaddleSpeed(paddle.speed)),
+            EffectSlot::Controls => Some(OriginalValue::Controls),
+            EffectSlot::BallSpeed => ball_query
+                .get(target)
+                .ok()
+                .map(|_| OriginalValue::BallSpeed),
+            EffectSlot::Instant => None,
+        };
+        if let Some(original) = original {
+            apply_effect(
+                &mut commands,
+                ev.kind,
+                target,
+                original,
+                &mut effects,
+                &mut paddle_query,
+                &mut ball_query,
+            );
+        }
+    }
+}
+
+fn apply_effect(
+    commands: &mut Commands,
+    kind: PowerUpKind,
+    target: Entity,
+    original: OriginalValue,
+    effects: &mut ActiveEffects,
+    paddle_query: &mut Query<(&mut Paddle, &mut Sprite, &mut Collider), Without<Ball>>,
+    ball_query: &mut Query<(&Transform, &mut Velocity), With<Ball>>,
+) {
+    match (kind, original) {
+        (PowerUpKind::ReversedControls, OriginalValue::Controls) => {
+            commands.entity(target).insert(ReversedControls);
+        }
+        (PowerUpKind::SlowBall, OriginalValue::BallSpeed) => {
+            if let Ok((_, mut velocity)) = ball_query.get_mut(target) {
+                velocity.linvel *= SLOW_BALL_SCALE;
+            }
+        }
+        _ => {
+            if !apply_paddle_effect(kind, target, original, paddle_query) {
+                return;
+            }
+        }
+    }
+
+    let effect = commands
+        .spawn()
+        .insert(ActiveEffect {
+            kind,
+            target,
+            original,
+        })
+        .insert(EffectDuration(Timer::from_seconds(kind.duration(), false)))
+        .id();
+    effects.0.push(effect);
+}
+
+/// Applies `kind` to the paddle on top of `original`, returning false for effects that don't
+/// act on paddles.
+fn apply_paddle_effect(
+    kind: PowerUpKind,
+    target: Entity,
+    original: OriginalValue,
+    paddle_query: &mut Query<(&mut Paddle, &mut Sprite, &mut Collider), Without<Ball>>,
+) -> bool {
+    match (kind, original) {
+        (PowerUpKind::BiggerPaddle, OriginalValue::PaddleSize(size)) => {
+            set_paddle_size(paddle_query, target, Vec2::new(size.x, size.y * 1.5));
+        }
+        (PowerUpKind::SmallerPaddle, OriginalValue::PaddleSize(size)) => {
+            set_paddle_size(paddle_query, target, Vec2::new(size.x, size.y * 0.6));
+        }
+        (PowerUpKind::FasterPaddle, OriginalValue::PaddleSpeed(speed)) => {
+            if let Ok((mut paddle, _, _)) = paddle_query.get_mut(target) {
+                paddle.speed = speed * 1.5;
+            }
+        }
+        _ => return false,
+    }
+    true
+}
+
+/// `apply_config` resets every paddle to a changed config, so paddle effects still running go
+/// back on top, with the new values as the ones restored on expiry.
+pub fn reapply_paddle_effects(
+    config: Res<PongConfig>,
+    mut effect_query: Query<&mut ActiveEffect>,
+    target_query: Query<&ActiveEffects, With<Paddle>>,
+    mut paddle_query: Query<(&mut Paddle, &mut Sprite, &mut Collider), Without<Ball>>,
+) {
+    if !config.is_changed() {
+        return;
+    }
+
+    for entity in target_query.iter().flat_map(|x| x.0.iter()) {
+        let mut effect = match effect_query.get_mut(*entity) {
+            Ok(effect) => effect,
+            Err(_) => continue,
+        };
+        let original = match effect.original {
+            OriginalValue::PaddleSize(_) => OriginalValue::PaddleSize(config.paddle.size),
+            OriginalValue::PaddleSpeed(_) => OriginalValue::PaddleSpeed(config.paddle.speed),
+            _ => continue,
+        };
+        effect.original = original;
+        apply_paddle_effect(effect.kind, effect.target, original, &mut paddle_query);
+    }
+}
+
+fn spawn_extra_ball(
+    commands: &mut Commands,
+    assets: &AssetServer,
+    config: &PongConfig,
+    transform: &Transform,
+    velocity: &Velocity,
+) {
+    // split off at an angle so both balls don't travel on top of each other
+    let velocity = rotate_vec2(velocity.linvel, 30.0_f32.to_radians());
+    let entity = spawn_launched_ball(
+        commands,
+        assets,
+        config,
+        transform.translation.truncate(),
+        velocity,
+    );
+    commands.entity(entity).insert(ExtraBall);
+}
+
+fn set_paddle_size(
+    paddle_query: &mut Query<(&mut Paddle, &mut Sprite, &mut Collider), Without<Ball>>,
+    target: Entity,
+    size: Vec2,
+) {
+    if let Ok((_, mut sprite, mut collider)) = paddle_query.get_mut(target) {
+        sprite.custom_size = Some(size);
+        *collider = Collider::cuboid(size.x / 2.0, size.y / 2.0);
+    }
+}
+
+pub fn expire_effects(
+    mut commands: Commands,
+    clock: Res<GameClock>,
+    mut effect_query: Query<(Entity, &ActiveEffect, &mut EffectDuration)>,
+    mut target_query: Query<&mut ActiveEffects>,
+    mut paddle_query: Query<(&mut Paddle, &mut Sprite, &mut Collider), Without<Ball>>,
+    mut ball_query: Query<&mut Velocity, With<Ball>>,
+) {
+    for (entity, effect, mut duration) in effect_query.iter_mut() {
+        duration.0.tick(clock.delta);
+        if duration.0.finished() {
+            restore_effect(&mut commands, effect, &mut paddle_query, &mut ball_query);
+            commands.entity(entity).despawn();
+            if let Ok(mut effects) = target_query.get_mut(effect.target) {
+                effects.0.retain(|x| *x != entity);
+            }
+        }
+    }
+}
+
+/// Ends every running effect and removes uncollected power-ups, for a fresh match.
+pub fn clear_power_ups(
+    mut commands: Commands,
+    mut spawner: ResMut<PowerUpSpawner>,
+    effect_query: Query<&ActiveEffect>,
+    mut target_query: Query<&mut ActiveEffects>,
+    power_up_query: Query<Entity, With<PowerUp>>,
+    mut paddle_query: Query<(&mut Paddle, &mut Sprite, &mut Collider), Without<Ball>>,
+    mut ball_query: Query<&mut Velocity, With<Ball>>,
+) {
+    for mut effects in target_query.iter_mut() {
+        for entity in effects.0.drain(..) {
+            if let Ok(effect) = effect_query.get(entity) {
+                restore_effect(&mut commands, effect, &mut paddle_query, &mut ball_query);
+                commands.entity(entity).despawn();
+            }
+        }
+    }
+    for entity in power_up_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    spawner.timer.reset();
+}
+
+fn restore_effect(
+    commands: &mut Commands,
+    effect: &ActiveEffect,
+    paddle_query: &mut Query<(&mut Paddle, &mut Sprite, &mut Collider), Without<Ball>>,
+    ball_query: &mut Query<&mut Velocity, With<Ball>>,
+) {
+    match effect.original {
+        OriginalValue::PaddleSize(size) => set_paddle_size(paddle_query, effect.target, size),
+        OriginalValue::PaddleSpeed(speed) => {
+            if let Ok((mut paddle, _, _)) = paddle_query.get_mut(effect.target) {
+                paddle.speed = speed;
+            }
+        }
+        OriginalValue::Controls => {
+            commands.entity(effect.target).remove::<ReversedControls>();
+        }
+        OriginalValue::BallSpeed => {
+            if let Ok(mut velocity) = ball_query.get_mut(effect.target) {
+                velocity.linvel /= SLOW_BALL_SCALE;
+            }
+        }
+    }
+}
@@ -0,0 +1,324 @@
+use super::{
+    ai::AiDifficulty,
+    config::{PongConfig, PongConfigSource},
+    game::Label,
+    game_entities::*,
+    input::{Axis, InputAxes},
+    rng::GameRng,
+    tick::{TickAppExt, TickStage},
+    utils::ByteReader,
+};
+use bevy::prelude::*;
+use iyes_loopless::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+const MAGIC: &[u8; 7] = b"PONGRPL";
+const VERSION: u8 = 1;
+const PLAYERS: usize = 2;
+/// Longest replay loaded, 24 hours at the tick rate. Run lengths come from the
+/// file, so they can't be trusted to fit in memory.
+pub const MAX_REPLAY_TICKS: usize = 24 * 60 * 60 * TICK_RATE as usize;
+
+/// Vertical axis values of both players during one tick, indexed by `PlayerId`.
+pub type TickInput = [f32; PLAYERS];
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io { path: PathBuf, source: io::Error },
+    Parse(ron::Error),
+    Serialize(ron::Error),
+    UnsupportedVersion(u8),
+    Corrupt(&'static str),
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::Io { path, source } => {
+                write!(
+                    f,
+                    "could not access replay at {}: {}",
+                    path.display(),
+                    source
+                )
+            }
+            ReplayError::Parse(source) => write!(f, "invalid replay header: {}", source),
+            ReplayError::Serialize(source) => {
+                write!(f, "could not serialize replay header: {}", source)
+            }
+            ReplayError::UnsupportedVersion(version) => {
+                write!(f, "replay version {} is not supported", version)
+            }
+            ReplayError::Corrupt(reason) => write!(f, "corrupt replay: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ReplayError::Io { source, .. } => Some(source),
+            ReplayError::Parse(source) | ReplayError::Serialize(source) => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Everything besides player input that decides how a match plays out.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReplayHeader {
+    pub seed: u64,
+    pub config: PongConfig,
+    pub match_mode: MatchMode,
+    pub ai_difficulty: AiDifficulty,
+    pub win_condition: WinCondition,
+    pub ball_count: usize,
+}
+
+/// A recorded match: its settings and the input of every tick spent playing.
+///
+/// On disk a replay is the `MAGIC` bytes and version, the header as length-prefixed RON and
+/// the inputs run-length encoded as little-endian `(u32 ticks, [f32; 2] input)` pairs, since
+/// input rarely changes from one tick to the next.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Replay {
+    pub header: ReplayHeader,
+    pub inputs: Vec<TickInput>,
+}
+
+impl Replay {
+    pub fn new(header: ReplayHeader) -> Self {
+        Replay {
+            header,
+            inputs: Vec::new(),
+        }
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>, ReplayError> {
+        let header = ron::to_string(&self.header).map_err(ReplayError::Serialize)?;
+        let mut runs: Vec<(u32, TickInput)> = Vec::new();
+        for input in self.inputs.iter() {
+            match runs.last_mut() {
+                Some((len, last)) if last == input && *len < u32::MAX => *len += 1,
+                _ => runs.push((1, *input)),
+            }
+        }
+
+        let mut bytes = Vec::with_capacity(MAGIC.len() + 9 + header.len() + runs.len() * 12);
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(VERSION);
+        bytes.extend_from_slice(&(header.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(header.as_bytes());
+        bytes.extend_from_slice(&(runs.len() as u32).to_le_bytes());
+        for (len, input) in runs {
+            bytes.extend_from_slice(&len.to_le_bytes());
+            for val in input {
+                bytes.extend_from_slice(&val.to_le_bytes());
+            }
+        }
+        Ok(bytes)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ReplayError> {
+        const TRUNCATED: ReplayError = ReplayError::Corrupt("unexpected end of file");
+        let mut reader = ByteReader(bytes);
+        if reader.take(MAGIC.len()).ok_or(TRUNCATED)? != MAGIC {
+            return Err(ReplayError::Corrupt("not a replay file"));
+        }
+        let version = reader.u8().ok_or(TRUNCATED)?;
+        if version != VERSION {
+            return Err(ReplayError::UnsupportedVersion(version));
+        }
+        let header_len = reader.u32().ok_or(TRUNCATED)? as usize;
+        let header = ron::de::from_bytes(reader.take(header_len).ok_or(TRUNCATED)?)
+            .map_err(ReplayError::Parse)?;
+
+        let mut inputs = Vec::new();
+        for _ in 0..reader.u32().ok_or(TRUNCATED)? {
+            let len = reader.u32().ok_or(TRUNCATED)? as usize;
+            let mut input = [0.0; PLAYERS];
+            for val in input.iter_mut() {
+                *val = reader.f32().ok_or(TRUNCATED)?;
+            }
+            if len > MAX_REPLAY_TICKS - inputs.len() {
+                return Err(ReplayError::Corrupt("too many ticks"));
+            }
+            inputs.resize(inputs.len() + len, input);
+        }
+        if !reader.is_empty() {
+            return Err(ReplayError::Corrupt("trailing bytes"));
+        }
+        Ok(Replay { header, inputs })
+    }
+
+    pub fn load(path: &Path) -> Result<Self, ReplayError> {
+        let bytes = fs::read(path).map_err(|source| ReplayError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Self::from_bytes(&bytes)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), ReplayError> {
+        let bytes = self.to_bytes()?;
+        let io_err = |source| ReplayError::Io {
+            path: path.to_path_buf(),
+            source,
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(io_err)?;
+        }
+        fs::write(path, bytes).map_err(io_err)
+    }
+}
+
+/// Records every match while present. The replay restarts with each match and is written to
+/// `path`, if set, once the match is over or abandoned.
+#[derive(Default)]
+pub struct ReplayRecorder {
+    pub path: Option<PathBuf>,
+    pub replay: Option<Replay>,
+}
+
+/// Plays back `replay` while present: the match starts right away with the recorded settings
+/// and the recorded input replaces the players' own. Once the recording runs out the paddles
+/// stand still.
+pub struct ReplayPlayer {
+    replay: Replay,
+    tick: usize,
+}
+
+impl ReplayPlayer {
+    pub fn new(replay: Replay) -> Self {
+        ReplayPlayer { replay, tick: 0 }
+    }
+
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+
+    pub fn finished(&self) -> bool {
+        self.tick >= self.replay.inputs.len()
+    }
+}
+
+pub struct PongReplay;
+impl Plugin for PongReplay {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system_to_stage(StartupStage::PreStartup, apply_replay_header)
+            // a netplay match reseeds the rng as it leaves the menu
+            .add_exit_system(GameState::MainMenu, start_recording.after(Label::NetInput))
+            .add_exit_system(GameState::GameOver, start_recording)
+            .add_enter_system(GameState::GameOver, finish_recording)
+            .add_enter_system(GameState::MainMenu, finish_recording)
+            .add_tick_system_set(
+                TickStage::Gameplay,
+                ConditionSet::new()
+                    .run_in_state(GameState::Playing)
+                    .run_if_resource_exists::<ReplayPlayer>()
+                    .label(Label::Replay)
+                    .after(Label::Clock)
+                    .before(Label::CollisionCheck)
+                    .with_system(play_back_inputs)
+                    .into(),
+            )
+            .add_tick_system_set(
+                TickStage::Gameplay,
+                ConditionSet::new()
+                    .run_in_state(GameState::Playing)
+                    .run_if_resource_exists::<ReplayRecorder>()
+                    .label(Label::Replay)
+                    .after(Label::Clock)
+                    // whatever netplay or the server put in
+                    .after(Label::NetInput)
+                    .before(Label::CollisionCheck)
+                    .with_system(record_inputs)
+                    .into(),
+            );
+    }
+}
+
+/// Sets up the recorded match before anything is spawned, skipping the main menu.
+pub fn apply_replay_header(mut commands: Commands, player: Option<Res<ReplayPlayer>>) {
+    let header = match player {
+        Some(player) => player.replay.header.clone(),
+        None => return,
+    };
+    commands.insert_resource(GameRng::new(header.seed));
+    commands.insert_resource(header.config);
+    // a config file edited in the meantime must not change the outcome
+    commands.insert_resource(PongConfigSource { path: None });
+    commands.insert_resource(header.match_mode);
+    commands.insert_resource(header.ai_difficulty);
+    commands.insert_resource(header.win_condition);
+    commands.insert_resource(BallCount(header.ball_count));
+    commands.insert_resource(NextState(GameState::Playing));
+}
+
+pub fn start_recording(
+    recorder: Option<ResMut<ReplayRecorder>>,
+    rng: Res<GameRng>,
+    config: Res<PongConfig>,
+    match_mode: Res<MatchMode>,
+    ai_difficulty: Res<AiDifficulty>,
+    win_condition: Res<WinCondition>,
+    ball_count: Res<BallCount>,
+) {
+    if let Some(mut recorder) = recorder {
+        recorder.replay = Some(Replay::new(ReplayHeader {
+            seed: rng.seed(),
+            config: config.clone(),
+            match_mode: *match_mode,
+            ai_difficulty: *ai_difficulty,
+            win_condition: win_condition.clone(),
+            ball_count: ball_count.0,
+        }));
+    }
+}
+
+pub fn finish_recording(recorder: Option<ResMut<ReplayRecorder>>) {
+    let mut recorder = match recorder {
+        Some(recorder) => recorder,
+        None => return,
+    };
+    let replay = match recorder.replay.take() {
+        Some(replay) if !replay.inputs.is_empty() => replay,
+        _ => return,
+    };
+    if let Some(path) = &recorder.path {
+        match replay.save(path) {
+            Ok(()) => info!("saved replay to {}", path.display()),
+            Err(e) => error!("{}", e),
+        }
+    }
+}
+
+pub fn record_inputs(mut recorder: ResMut<ReplayRecorder>, input_axes: Query<&InputAxes>) {
+    if let Some(replay) = recorder.replay.as_mut() {
+        let mut input = [0.0; PLAYERS];
+        for axes in input_axes.iter() {
+            input[axes.player as usize] = axes.val.get(&Axis::Vertical).map_or(0.0, |x| x.val);
+        }
+        replay.inputs.push(input);
+    }
+}
+
+pub fn play_back_inputs(mut player: ResMut<ReplayPlayer>, mut input_axes: Query<&mut InputAxes>) {
+    let input = player
+        .replay
+        .inputs
+        .get(player.tick)
+        .copied()
+        .unwrap_or_default();
+    player.tick += 1;
+    for mut axes in input_axes.iter_mut() {
+        let val = input[axes.player as usize];
+        if let Some(axis) = axes.val.get_mut(&Axis::Vertical) {
+            axis.val = val;
+        }
+    }
+}
@@ -0,0 +1,449 @@
+use super::{
+    game::Label,
+    game_entities::*,
+    input::{Axis, InputAxes, PlayerId},
+    tick::{TickAppExt, TickStage},
+};
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use iyes_loopless::prelude::*;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    time::Duration,
+};
+
+/// Longest line a client may send, anything longer drops it.
+const MAX_MESSAGE_SIZE: usize = 4096;
+/// Bytes of unsent snapshots a client may fall behind by before it's dropped.
+const MAX_BACKLOG_SIZE: usize = 1 << 20;
+/// How long the final score of a match is shown before the next one starts.
+pub const REMATCH_DELAY: Duration = Duration::from_secs(3);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ClientRole {
+    Player,
+    Spectator,
+}
+
+/// Messages travel as one line of RON each, in both directions.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ClientMessage {
+    /// First message of every client. Players past the second join as spectators.
+    Join(ClientRole),
+    /// Vertical axis of a player, held until the next one. Ignored from spectators.
+    Input(f32),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ServerMessage {
+    /// Reply to `Join`, with the paddle the client controls, if any.
+    Welcome {
+        player: Option<PlayerId>,
+    },
+    Snapshot(MatchSnapshot),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BodySnapshot {
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub velocity: Vec2,
+}
+
+/// The match as the server last simulated it. Balls are in spawn order and `score` is
+/// indexed by `Team`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MatchSnapshot {
+    pub tick: u64,
+    pub state: GameState,
+    pub balls: Vec<BodySnapshot>,
+    pub paddles: Vec<(PlayerId, BodySnapshot)>,
+    pub score: [i32; 2],
+}
+
+fn encode<T: Serialize>(message: &T) -> Vec<u8> {
+    let mut line = ron::to_string(message).expect("messages always serialize");
+    line.push('\n');
+    line.into_bytes()
+}
+
+fn decode<T: DeserializeOwned>(line: &[u8]) -> Option<T> {
+    ron::de::from_bytes(line).ok()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ClientKind {
+    Joining,
+    Player(PlayerId),
+    Spectator,
+}
+
+struct Client {
+    stream: TcpStream,
+    addr: SocketAddr,
+    kind: ClientKind,
+    incoming: Vec<u8>,
+    outgoing: Vec<u8>,
+    connected: bool,
+}
+
+impl Client {
+    fn receive(&mut self) -> Vec<ClientMessage> {
+        let mut buf = [0; 1024];
+        loop {
+            match self.stream.read(&mut buf) {
+                Ok(0) => {
+                    self.connected = false;
+                    break;
+                }
+                Ok(len) => self.incoming.extend_from_slice(&buf[..len]),
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => (),
+                Err(_) => {
+                    self.connected = false;
+                    break;
+                }
+            }
+        }
+
+        let mut messages = Vec::new();
+        while let Some(end) = self.incoming.iter().position(|x| *x == b'\n') {
+            let line: Vec<u8> = self.incoming.drain(..=end).collect();
+            match decode(&line) {
+                Some(message) => messages.push(message),
+                None => {
+                    warn!("{} sent an invalid message, dropping it", self.addr);
+                    self.connected = false;
+                }
+            }
+        }
+        if self.incoming.len() > MAX_MESSAGE_SIZE {
+            self.connected = false;
+        }
+        messages
+    }
+
+    fn send(&mut self, message: &ServerMessage) {
+        self.outgoing.extend(encode(message));
+    }
+
+    fn flush(&mut self) {
+        while !self.outgoing.is_empty() {
+            match self.stream.write(&self.outgoing) {
+                Ok(0) => {
+                    self.connected = false;
+                    return;
+                }
+                Ok(len) => {
+                    self.outgoing.drain(..len);
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => (),
+                Err(_) => {
+                    self.connected = false;
+                    return;
+                }
+            }
+        }
+        if self.outgoing.len() > MAX_BACKLOG_SIZE {
+            warn!("{} fell too far behind, dropping it", self.addr);
+            self.connected = false;
+        }
+    }
+}
+
+/// Runs an authoritative two player match for clients connecting over TCP while present.
+/// The match starts once both players have joined, and every update sends the state of the
+/// match to every client, players and spectators alike. A player leaving frees their paddle
+/// for the next one to join, and it stands still until then. `REMATCH_DELAY` after a match
+/// is won the next one starts, or waits for players again if one of them left.
+pub struct GameServer {
+    listener: TcpListener,
+    clients: Vec<Client>,
+    inputs: [f32; 2],
+    rematch_delay: Timer,
+}
+
+impl GameServer {
+    pub fn bind(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        Ok(GameServer {
+            listener,
+            clients: Vec::new(),
+            inputs: [0.0; 2],
+            rematch_delay: Timer::new(REMATCH_DELAY, false),
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    pub fn player_count(&self) -> usize {
+        self.players().count()
+    }
+
+    pub fn spectator_count(&self) -> usize {
+        self.clients
+            .iter()
+            .filter(|x| x.kind == ClientKind::Spectator)
+            .count()
+    }
+
+    fn players(&self) -> impl Iterator<Item = PlayerId> + '_ {
+        self.clients.iter().filter_map(|x| match x.kind {
+            ClientKind::Player(player) => Some(player),
+            _ => None,
+        })
+    }
+
+    fn accept(&mut self) {
+        loop {
+            match self.listener.accept() {
+                Ok((stream, addr)) => {
+                    if let Err(e) = stream
+                        .set_nonblocking(true)
+                        .and_then(|_| stream.set_nodelay(true))
+                    {
+                        warn!("could not set up connection from {}: {}", addr, e);
+                        continue;
+                    }
+                    info!("{} connected", addr);
+                    self.clients.push(Client {
+                        stream,
+                        addr,
+                        kind: ClientKind::Joining,
+                        incoming: Vec::new(),
+                        outgoing: Vec::new(),
+                        connected: true,
+                    });
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) => {
+                    warn!("could not accept connection: {}", e);
+                    break;
+                }
+            }
+        }
+    }
+
+    fn receive(&mut self) {
+        for i in 0..self.clients.len() {
+            for message in self.clients[i].receive() {
+                match (self.clients[i].kind, message) {
+                    (ClientKind::Joining, ClientMessage::Join(role)) => {
+                        let free_player = [PlayerId::One, PlayerId::Two]
+                            .into_iter()
+                            .find(|x| !self.players().any(|y| y == *x));
+                        let kind = match (role, free_player) {
+                            (ClientRole::Player, Some(player)) => ClientKind::Player(player),
+                            _ => ClientKind::Spectator,
+                        };
+                        let client = &mut self.clients[i];
+                        client.kind = kind;
+                        client.send(&ServerMessage::Welcome {
+                            player: match kind {
+                                ClientKind::Player(player) => Some(player),
+                                _ => None,
+                            },
+                        });
+                    }
+                    // a NaN would stick in the simulation for good, clamped or not
+                    (_, ClientMessage::Input(val)) if !val.is_finite() => {
+                        warn!(
+                            "{} sent an invalid input, dropping it",
+                            self.clients[i].addr
+                        );
+                        self.clients[i].connected = false;
+                        break;
+                    }
+                    (ClientKind::Player(player), ClientMessage::Input(val)) => {
+                        self.inputs[player as usize] = val.clamp(-1.0, 1.0);
+                    }
+                    _ => (),
+                }
+            }
+        }
+
+        for client in self.clients.iter().filter(|x| !x.connected) {
+            info!("{} disconnected", client.addr);
+            if let ClientKind::Player(player) = client.kind {
+                self.inputs[player as usize] = 0.0;
+            }
+        }
+        self.clients.retain(|x| x.connected);
+    }
+
+    fn broadcast(&mut self, message: &ServerMessage) {
+        for client in self.clients.iter_mut() {
+            if client.kind != ClientKind::Joining {
+                client.send(message);
+            }
+            client.flush();
+        }
+        self.clients.retain(|x| x.connected);
+    }
+}
+
+/// A client's end of a `GameServer` connection, blocking on every call.
+pub struct ServerConnection {
+    stream: TcpStream,
+    reader: BufReader<TcpStream>,
+}
+
+impl ServerConnection {
+    pub fn connect(addr: impl ToSocketAddrs, role: ClientRole) -> io::Result<Self> {
+        let stream = TcpStream::connect(addr)?;
+        stream.set_nodelay(true)?;
+        let mut connection = ServerConnection {
+            reader: BufReader::new(stream.try_clone()?),
+            stream,
+        };
+        connection.send(&ClientMessage::Join(role))?;
+        Ok(connection)
+    }
+
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.stream.set_read_timeout(timeout)
+    }
+
+    pub fn send(&mut self, message: &ClientMessage) -> io::Result<()> {
+        self.stream.write_all(&encode(message))
+    }
+
+    pub fn receive(&mut self) -> io::Result<ServerMessage> {
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        decode(line.as_bytes())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid server message"))
+    }
+}
+
+pub struct PongServer;
+impl Plugin for PongServer {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system_to_stage(StartupStage::PreStartup, prepare_server_match)
+            .add_system(update_game_server.run_if_resource_exists::<GameServer>())
+            .add_system_to_stage(
+                CoreStage::Last,
+                broadcast_snapshot.run_if_resource_exists::<GameServer>(),
+            )
+            .add_tick_system_set(
+                TickStage::Gameplay,
+                ConditionSet::new()
+                    .run_in_state(GameState::Playing)
+                    .run_if_resource_exists::<GameServer>()
+                    .label(Label::NetInput)
+                    .after(Label::Clock)
+                    .before(Label::CollisionCheck)
+                    .with_system(apply_server_inputs)
+                    .into(),
+            )
+            .add_tick_system_set(
+                TickStage::Gameplay,
+                ConditionSet::new()
+                    .run_in_state(GameState::GameOver)
+                    .run_if_resource_exists::<GameServer>()
+                    .with_system(wait_for_rematch)
+                    .into(),
+            );
+    }
+}
+
+/// Holds the game in the main menu until both players have joined.
+pub fn prepare_server_match(mut commands: Commands, server: Option<Res<GameServer>>) {
+    if server.is_none() {
+        return;
+    }
+    commands.remove_resource::<NextState<GameState>>();
+    commands.insert_resource(MatchMode::TwoPlayer);
+}
+
+pub fn update_game_server(
+    mut commands: Commands,
+    mut server: ResMut<GameServer>,
+    state: Res<CurrentState<GameState>>,
+) {
+    server.accept();
+    server.receive();
+    match state.0 {
+        GameState::MainMenu if server.player_count() == 2 => {
+            info!("both players joined, starting the match");
+            commands.insert_resource(NextState(GameState::Playing));
+        }
+        // nobody is around to press a key on the game over screen
+        GameState::GameOver => {
+            if !server.rematch_delay.finished() {
+                return;
+            }
+            server.rematch_delay.reset();
+            if server.player_count() == 2 {
+                info!("starting a rematch");
+                commands.insert_resource(NextState(GameState::Playing));
+            } else {
+                info!("a player left, waiting for players");
+                commands.insert_resource(NextState(GameState::MainMenu));
+            }
+        }
+        _ => (),
+    }
+}
+
+/// The game clock stands still outside of matches, so the wait for a rematch counts ticks
+/// of its own.
+pub fn wait_for_rematch(clock: Res<GameClock>, mut server: ResMut<GameServer>) {
+    server.rematch_delay.tick(clock.step);
+}
+
+pub fn apply_server_inputs(server: Res<GameServer>, mut input_axes: Query<&mut InputAxes>) {
+    for mut axes in input_axes.iter_mut() {
+        let val = server.inputs[axes.player as usize];
+        if let Some(axis) = axes.val.get_mut(&Axis::Vertical) {
+            axis.val = val;
+        }
+    }
+}
+
+fn body_snapshot(transform: &Transform, velocity: &Velocity) -> BodySnapshot {
+    BodySnapshot {
+        translation: transform.translation,
+        rotation: transform.rotation,
+        velocity: velocity.linvel,
+    }
+}
+
+pub fn broadcast_snapshot(
+    mut server: ResMut<GameServer>,
+    clock: Res<GameClock>,
+    state: Res<CurrentState<GameState>>,
+    balls: Query<(Entity, &Transform, &Velocity), With<Ball>>,
+    paddles: Query<(&PlayerId, &Transform, &Velocity), With<Paddle>>,
+    match_score: Query<&MatchScore>,
+) {
+    let mut balls: Vec<_> = balls.iter().collect();
+    balls.sort_by_key(|x| x.0);
+    let mut paddles: Vec<_> = paddles
+        .iter()
+        .map(|(player, transform, velocity)| (*player, body_snapshot(transform, velocity)))
+        .collect();
+    paddles.sort_by_key(|x| x.0 as usize);
+    let score = match_score.iter().next().map_or([0; 2], |x| {
+        [Team::Player, Team::AI].map(|team| x.score.get(&team).copied().unwrap_or_default())
+    });
+
+    server.broadcast(&ServerMessage::Snapshot(MatchSnapshot {
+        tick: clock.tick,
+        state: state.0,
+        balls: balls
+            .into_iter()
+            .map(|(_, transform, velocity)| body_snapshot(transform, velocity))
+            .collect(),
+        paddles,
+        score,
+    }));
+}
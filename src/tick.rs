@@ -0,0 +1,202 @@
+use super::game_entities::TickMode;
+use bevy::{
+    ecs::schedule::{IntoSystemDescriptor, StageLabel},
+    prelude::*,
+    transform::TransformSystem,
+};
+use std::time::Duration;
+
+/// Label of the stage running gameplay and physics at the fixed tick rate.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, StageLabel)]
+pub struct FixedTick;
+
+/// Child stages of `FixedTickStage`, run in this order on every tick.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TickStage {
+    Gameplay,
+    PhysicsSync,
+    PhysicsStep,
+    PhysicsWriteback,
+    PostTick,
+}
+
+impl TickStage {
+    const ALL: [TickStage; 5] = [
+        TickStage::Gameplay,
+        TickStage::PhysicsSync,
+        TickStage::PhysicsStep,
+        TickStage::PhysicsWriteback,
+        TickStage::PostTick,
+    ];
+}
+
+/// How far rendering is between the last two ticks, as a fraction of a tick.
+#[derive(Default)]
+pub struct TickOverstep(pub f32);
+
+/// Number of past ticks to run again, right away, before any new one. Inserted by whoever
+/// restored the world to an earlier tick.
+pub struct Resimulate(pub u32);
+
+/// Keeps new ticks from running while present, e.g. while waiting on a network peer. Time
+/// stands still meanwhile instead of piling up.
+pub struct TickHold;
+
+/// Most ticks `TickMode::RealTime` runs in a single frame. Time beyond this after a stall,
+/// e.g. a dragged window, is dropped rather than caught up with ever longer frames.
+pub const MAX_CATCH_UP_TICKS: u32 = 5;
+
+/// Runs its child stages once per tick. With `TickMode::RealTime` the frame time is
+/// accumulated and as many ticks run as fit into it, with `TickMode::PerUpdate` every run
+/// is exactly one tick.
+pub struct FixedTickStage {
+    mode: TickMode,
+    step: Duration,
+    accumulator: Duration,
+    stages: Vec<SystemStage>,
+}
+
+impl FixedTickStage {
+    pub fn new(mode: TickMode, step: Duration) -> Self {
+        FixedTickStage {
+            mode,
+            step,
+            accumulator: Duration::ZERO,
+            stages: TickStage::ALL
+                .iter()
+                .map(|_| SystemStage::parallel())
+                .collect(),
+        }
+    }
+
+    pub fn stage_mut(&mut self, stage: TickStage) -> &mut SystemStage {
+        &mut self.stages[stage as usize]
+    }
+
+    fn run_tick(&mut self, world: &mut World) {
+        for stage in self.stages.iter_mut() {
+            stage.run(world);
+        }
+    }
+}
+
+impl Stage for FixedTickStage {
+    fn run(&mut self, world: &mut World) {
+        if let Some(Resimulate(ticks)) = world.remove_resource::<Resimulate>() {
+            for _ in 0..ticks {
+                self.run_tick(world);
+            }
+        }
+        if world.contains_resource::<TickHold>() {
+            return;
+        }
+
+        match self.mode {
+            TickMode::PerUpdate => self.run_tick(world),
+            TickMode::RealTime => {
+                self.accumulator += world
+                    .get_resource::<Time>()
+                    .map_or(Duration::ZERO, |x| x.delta());
+                self.accumulator = self.accumulator.min(self.step * MAX_CATCH_UP_TICKS);
+                while self.accumulator >= self.step {
+                    self.accumulator -= self.step;
+                    self.run_tick(world);
+                }
+            }
+        }
+
+        let overstep = self.accumulator.as_secs_f32() / self.step.as_secs_f32();
+        world.insert_resource(TickOverstep(overstep));
+    }
+}
+
+pub trait TickAppExt {
+    fn add_tick_system<Params>(
+        &mut self,
+        stage: TickStage,
+        system: impl IntoSystemDescriptor<Params>,
+    ) -> &mut Self;
+    fn add_tick_system_set(&mut self, stage: TickStage, set: SystemSet) -> &mut Self;
+}
+
+impl TickAppExt for App {
+    fn add_tick_system<Params>(
+        &mut self,
+        stage: TickStage,
+        system: impl IntoSystemDescriptor<Params>,
+    ) -> &mut Self {
+        fixed_tick_stage(self).stage_mut(stage).add_system(system);
+        self
+    }
+
+    fn add_tick_system_set(&mut self, stage: TickStage, set: SystemSet) -> &mut Self {
+        fixed_tick_stage(self).stage_mut(stage).add_system_set(set);
+        self
+    }
+}
+
+fn fixed_tick_stage(app: &mut App) -> &mut FixedTickStage {
+    app.schedule
+        .get_stage_mut::<FixedTickStage>(&FixedTick)
+        .expect("FixedTickStage not found, add the PongGame plugin first")
+}
+
+/// Physics positions of an entity at the last two ticks, used to draw it smoothly in between
+/// when frames and ticks don't line up.
+#[derive(Clone, Component, Default)]
+pub struct TickInterpolation {
+    previous: Option<Transform>,
+    current: Option<Transform>,
+}
+
+pub fn record_physics_transforms(mut query: Query<(&Transform, &mut TickInterpolation)>) {
+    for (transform, mut interpolation) in query.iter_mut() {
+        interpolation.previous = interpolation.current.or(Some(*transform));
+        interpolation.current = Some(*transform);
+    }
+}
+
+/// Puts back the physics positions replaced by `interpolate_transforms` for rendering, before
+/// anything else gets to look at them.
+pub fn restore_physics_transforms(mut query: Query<(&mut Transform, &TickInterpolation)>) {
+    for (mut transform, interpolation) in query.iter_mut() {
+        if let Some(current) = interpolation.current {
+            transform.translation = current.translation;
+            transform.rotation = current.rotation;
+        }
+    }
+}
+
+/// Draws entities between their last two physics positions. Entities moved outside the tick
+/// since, e.g. by a layout change, are left where they were put.
+pub fn interpolate_transforms(
+    overstep: Res<TickOverstep>,
+    mut query: Query<(&mut Transform, &mut TickInterpolation)>,
+) {
+    let t = overstep.0.clamp(0.0, 1.0);
+    for (mut transform, mut interpolation) in query.iter_mut() {
+        if let (Some(previous), Some(current)) = (interpolation.previous, interpolation.current) {
+            if transform.translation != current.translation
+                || transform.rotation != current.rotation
+            {
+                interpolation.previous = Some(*transform);
+                interpolation.current = Some(*transform);
+                continue;
+            }
+            transform.translation = previous.translation.lerp(current.translation, t);
+            transform.rotation = previous.rotation.slerp(current.rotation, t);
+        }
+    }
+}
+
+/// Adds the systems smoothing out `TickInterpolation` entities between ticks. Only
+/// needed when ticks follow wall-clock time.
+pub fn add_interpolation_systems(app: &mut App) {
+    app.init_resource::<TickOverstep>()
+        .add_tick_system(TickStage::PostTick, record_physics_transforms)
+        .add_system_to_stage(CoreStage::First, restore_physics_transforms)
+        .add_system_to_stage(
+            CoreStage::PostUpdate,
+            interpolate_transforms.before(TransformSystem::TransformPropagate),
+        );
+}
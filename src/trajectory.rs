@@ -0,0 +1,71 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::Velocity;
+
+/// Playable area between the inner faces of the walls spawned by `spawn_bounds`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ArenaBounds {
+    pub min: Vec2,
+    pub max: Vec2,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Intercept {
+    /// Ball center when it reaches the plane.
+    pub point: Vec2,
+    /// Seconds from now until the ball reaches the plane.
+    pub time: f32,
+    /// Ball velocity at the plane, after any reflections.
+    pub velocity: Vec2,
+    pub bounces: u32,
+}
+
+/// Predicts where a ball of `radius` will cross the vertical line `x = plane_x`, reflecting
+/// off the top and bottom of `bounds`. Returns `None` if the ball never reaches the plane.
+pub fn predict_intercept(
+    position: Vec2,
+    velocity: &Velocity,
+    radius: f32,
+    bounds: &ArenaBounds,
+    plane_x: f32,
+) -> Option<Intercept> {
+    let v = velocity.linvel;
+    if v.x == 0.0 {
+        return None;
+    }
+
+    let time = (plane_x - position.x) / v.x;
+    if time < 0.0 {
+        return None;
+    }
+
+    let lower = bounds.min.y + radius;
+    let upper = bounds.max.y - radius;
+    let span = upper - lower;
+    let unfolded_y = position.y + v.y * time;
+    if span <= 0.0 {
+        return Some(Intercept {
+            point: Vec2::new(plane_x, (bounds.min.y + bounds.max.y) / 2.0),
+            time,
+            velocity: v,
+            bounces: 0,
+        });
+    }
+
+    // each wall reflection mirrors the path, so walk the straight line and fold it back
+    let offset = unfolded_y - lower;
+    let folds = (offset / span).floor();
+    let remainder = offset - folds * span;
+    let reflected = folds as i64 % 2 != 0;
+    let (y, vy) = if reflected {
+        (upper - remainder, -v.y)
+    } else {
+        (lower + remainder, v.y)
+    };
+
+    Some(Intercept {
+        point: Vec2::new(plane_x, y),
+        time,
+        velocity: Vec2::new(v.x, vy),
+        bounces: folds.abs() as u32,
+    })
+}
@@ -10,22 +10,30 @@ where
     values.iter().sum::<T>() / (values.len() as f32)
 }
 
-pub fn rand_sign() -> f32 {
-    if random::<f32>() >= 0.5 {
+pub fn rand_sign(rng: &mut impl Rng) -> f32 {
+    if rng.gen::<f32>() >= 0.5 {
         1.0
     } else {
         -1.0
     }
 }
 
-pub fn random_horizontal() -> Vec2 {
-    Vec2::new(rand_sign(), 0.0)
+pub fn random_horizontal(rng: &mut impl Rng) -> Vec2 {
+    Vec2::new(rand_sign(rng), 0.0)
 }
 
 pub fn approx_eq(a: f32, b: f32, margin: f32) -> bool {
     (a - b).abs() <= margin
 }
 
+pub fn sign_or_zero(v: f32) -> f32 {
+    if v == 0.0 {
+        0.0
+    } else {
+        v.signum()
+    }
+}
+
 pub fn inverse_lerp(a: f32, b: f32, value: f32) -> f32 {
     if a != b {
         clamp_01((value - a) / (b - a))
@@ -35,13 +43,7 @@ pub fn inverse_lerp(a: f32, b: f32, value: f32) -> f32 {
 }
 
 pub fn clamp_01(v: f32) -> f32 {
-    if v < 0.0 {
-        0.0
-    } else if v > 1.0 {
-        1.0
-    } else {
-        v
-    }
+    v.clamp(0.0, 1.0)
 }
 
 pub fn rotate_vec2(v: Vec2, radians: f32) -> Vec2 {
@@ -52,6 +54,45 @@ pub fn rotate_vec2(v: Vec2, radians: f32) -> Vec2 {
     Vec2::new(x, y)
 }
 
+/// Moves `from` towards `to` by at most `max_delta`, without overshooting.
+pub fn move_towards(from: f32, to: f32, max_delta: f32) -> f32 {
+    from + (to - from).clamp(-max_delta, max_delta)
+}
+
 pub fn lerp(a: f32, b: f32, t: f32) -> f32 {
     a + (b - a) * clamp_01(t)
 }
+
+/// Reads little-endian values off the front of a byte slice, `None` once it runs out.
+pub struct ByteReader<'a>(pub &'a [u8]);
+
+impl<'a> ByteReader<'a> {
+    pub fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        if self.0.len() < len {
+            return None;
+        }
+        let (taken, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Some(taken)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn u8(&mut self) -> Option<u8> {
+        Some(self.take(1)?[0])
+    }
+
+    pub fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_le_bytes(self.take(4)?.try_into().ok()?))
+    }
+
+    pub fn u64(&mut self) -> Option<u64> {
+        Some(u64::from_le_bytes(self.take(8)?.try_into().ok()?))
+    }
+
+    pub fn f32(&mut self) -> Option<f32> {
+        Some(f32::from_le_bytes(self.take(4)?.try_into().ok()?))
+    }
+}
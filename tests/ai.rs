@@ -0,0 +1,123 @@
+use bevy::prelude::*;
+use bevy_pong::{
+    ai::*, headless, rng::GameRng, trajectory::predict_intercept, trajectory::ArenaBounds,
+};
+use bevy_rapier2d::prelude::*;
+
+const PADDLE_X: f32 = 460.0;
+
+fn arena() -> ArenaBounds {
+    ArenaBounds {
+        min: Vec2::new(-960.0, -540.0),
+        max: Vec2::new(960.0, 540.0),
+    }
+}
+
+/// A still paddle in the middle of its line and a ball at `ball_position`.
+fn context(ball_position: Vec2, ball_velocity: Vec2) -> AiContext {
+    AiContext {
+        paddle_position: Vec2::new(PADDLE_X, 0.0),
+        paddle_velocity: 0.0,
+        paddle_deceleration: 8000.0,
+        ball_position,
+        ball_velocity,
+        ball_radius: 12.5,
+        arena: arena(),
+        elapsed: 0.0,
+        time_since_velocity_change: 10.0,
+    }
+}
+
+/// Above the paddle now, but bouncing off the top wall to cross its line far below.
+fn steep_shot() -> AiContext {
+    context(Vec2::new(0.0, 300.0), Vec2::new(400.0, 900.0))
+}
+
+#[test]
+fn predictor_heads_for_the_intercept() {
+    let ctx = steep_shot();
+    let intercept = predict_intercept(
+        ctx.ball_position,
+        &Velocity::linear(ctx.ball_velocity),
+        ctx.ball_radius,
+        &ctx.arena,
+        PADDLE_X,
+    )
+    .unwrap();
+    assert!(intercept.point.y < -100.0);
+
+    let mut rng = GameRng::new(headless::SEED);
+    let mut predictor = TrajectoryPredictor { deadzone: 10.0 };
+    assert_eq!(predictor.decide(&ctx, &mut rng), -1.0);
+
+    // and back to the center while the ball moves away
+    let mut ctx = context(Vec2::new(0.0, 300.0), Vec2::new(-400.0, 0.0));
+    ctx.paddle_position.y = 200.0;
+    assert_eq!(predictor.decide(&ctx, &mut rng), -1.0);
+}
+
+#[test]
+fn chaser_follows_the_ball_outside_its_deadzone() {
+    let mut rng = GameRng::new(headless::SEED);
+    let mut chaser = ReactiveChaser::default();
+    let ctx = steep_shot();
+    assert_eq!(chaser.decide(&ctx, &mut rng), 1.0);
+    let ctx = context(Vec2::new(0.0, chaser.deadzone / 2.0), Vec2::X * 400.0);
+    assert_eq!(chaser.decide(&ctx, &mut rng), 0.0);
+}
+
+#[test]
+fn human_like_rethinks_only_after_its_reaction_delay() {
+    let mut rng = GameRng::new(headless::SEED);
+    let mut ai = HumanLike::default();
+    ai.reaction_delay = 0.2;
+    ai.max_error = 0.0;
+    let mut ctx = context(Vec2::new(0.0, 300.0), Vec2::X * 400.0);
+    assert_eq!(ai.decide(&ctx, &mut rng), 1.0);
+
+    // the ball jumped below, but the old target still stands
+    ctx.ball_position.y = -300.0;
+    ctx.elapsed = 0.1;
+    assert_eq!(ai.decide(&ctx, &mut rng), 1.0);
+    ctx.elapsed = 0.2;
+    assert_eq!(ai.decide(&ctx, &mut rng), -1.0);
+}
+
+#[test]
+fn human_like_cannot_turn_around_right_after_changing_direction() {
+    let mut rng = GameRng::new(headless::SEED);
+    let mut ai = HumanLike::default();
+    ai.reaction_delay = 0.2;
+    ai.max_error = 0.0;
+    let mut ctx = context(Vec2::new(0.0, -300.0), Vec2::X * 400.0);
+    ctx.paddle_velocity = 300.0;
+    ctx.time_since_velocity_change = 0.05;
+    assert_eq!(ai.decide(&ctx, &mut rng), 1.0);
+
+    ctx.time_since_velocity_change = 0.2;
+    assert_eq!(ai.decide(&ctx, &mut rng), -1.0);
+}
+
+#[test]
+fn harder_presets_read_the_ball_further_ahead() {
+    let ctx = steep_shot();
+    let decide = |difficulty: AiDifficulty| {
+        let mut rng = GameRng::new(headless::SEED);
+        difficulty.controller().decide(&ctx, &mut rng)
+    };
+    // easy chases where the ball is, the others where it's going
+    assert_eq!(decide(AiDifficulty::Easy), 1.0);
+    assert_eq!(decide(AiDifficulty::Normal), -1.0);
+    assert_eq!(decide(AiDifficulty::Hard), -1.0);
+}
+
+#[test]
+fn moving_paddles_let_go_before_their_target() {
+    let mut rng = GameRng::new(headless::SEED);
+    let mut predictor = TrajectoryPredictor { deadzone: 10.0 };
+    let mut ctx = context(Vec2::new(0.0, 300.0), Vec2::new(-400.0, 0.0));
+    // 20 units from the center at a speed that needs more than that to stop
+    ctx.paddle_position.y = 20.0;
+    ctx.paddle_velocity = -1000.0;
+    assert_eq!(predictor.decide(&ctx, &mut rng), 0.0);
+}
@@ -0,0 +1,119 @@
+use bevy::{
+    asset::HandleId,
+    ecs::event::Events,
+    input::{keyboard::KeyboardInput, ElementState},
+    prelude::*,
+};
+use bevy_pong::{
+    audio::{PongAudio, MUTE_KEY},
+    config::PongConfig,
+    game_entities::*,
+    headless,
+};
+
+const GOAL: &str = "sounds/goal.ogg";
+const COUNTDOWN: &str = "sounds/countdown.ogg";
+
+/// A headless app playing sounds into an `Audio` queue nothing drains, so everything played
+/// stays there to be counted.
+fn audio_app() -> App {
+    let mut app = headless::headless_app();
+    let mut config = PongConfig::default();
+    config.audio.goal = Some(GOAL.to_string());
+    config.audio.countdown = Some(COUNTDOWN.to_string());
+    app.insert_resource(config)
+        .insert_resource(Audio::<AudioSource>::default())
+        .add_plugin(PongAudio);
+    app
+}
+
+fn times_played(app: &App, path: &str) -> usize {
+    let queue = format!("{:?}", app.world.resource::<Audio>());
+    queue
+        .matches(&format!("{:?}", HandleId::from(path)))
+        .count()
+}
+
+fn tap(app: &mut App, key: KeyCode) {
+    for state in [ElementState::Pressed, ElementState::Released] {
+        app.world
+            .resource_mut::<Events<KeyboardInput>>()
+            .send(KeyboardInput {
+                scan_code: 0,
+                key_code: Some(key),
+                state,
+            });
+        app.update();
+    }
+}
+
+fn score_goal(app: &mut App) {
+    let mut balls = app.world.query_filtered::<Entity, With<Ball>>();
+    let ball = balls.iter(&app.world).next().unwrap();
+    app.world
+        .resource_mut::<Events<GoalEvent>>()
+        .send(GoalEvent {
+            ball,
+            team: Team::Player,
+        });
+    app.update();
+}
+
+fn wait_for_launch(app: &mut App) {
+    let delay = app.world.resource::<PongConfig>().ball.launch_delay;
+    headless::run_ticks(app, (delay * headless::TICK_RATE as f32) as u32 + 2);
+}
+
+#[test]
+fn countdowns_play_whenever_a_ball_waits_to_launch() {
+    let mut app = audio_app();
+    app.insert_resource(WinCondition {
+        target_score: 2,
+        win_by: 1,
+    });
+    headless::run_ticks(&mut app, 2);
+    assert_eq!(times_played(&app, COUNTDOWN), 1, "match start");
+
+    wait_for_launch(&mut app);
+    score_goal(&mut app);
+    headless::run_ticks(&mut app, 2);
+    assert_eq!(times_played(&app, GOAL), 1);
+    assert_eq!(times_played(&app, COUNTDOWN), 2, "after a goal");
+
+    app.world
+        .resource_mut::<Events<SpawnBall>>()
+        .send(SpawnBall {
+            position: Vec2::ZERO,
+            velocity: None,
+        });
+    app.world
+        .resource_mut::<Events<SpawnBall>>()
+        .send(SpawnBall {
+            position: Vec2::ZERO,
+            velocity: Some(Vec2::X * 300.0),
+        });
+    headless::run_ticks(&mut app, 2);
+    assert_eq!(times_played(&app, COUNTDOWN), 3, "waiting balls only");
+
+    score_goal(&mut app);
+    headless::run_ticks(&mut app, 2);
+    tap(&mut app, KeyCode::Space);
+    headless::run_ticks(&mut app, 2);
+    assert_eq!(times_played(&app, COUNTDOWN), 4, "rematch");
+}
+
+#[test]
+fn muted_sounds_are_skipped_not_saved_for_later() {
+    let mut app = audio_app();
+    headless::run_ticks(&mut app, 2);
+    tap(&mut app, MUTE_KEY);
+    score_goal(&mut app);
+    headless::run_ticks(&mut app, 2);
+    assert_eq!(times_played(&app, GOAL), 0);
+
+    tap(&mut app, MUTE_KEY);
+    headless::run_ticks(&mut app, 2);
+    assert_eq!(times_played(&app, GOAL), 0);
+    score_goal(&mut app);
+    assert_eq!(times_played(&app, GOAL), 1);
+}
@@ -0,0 +1,56 @@
+use bevy::prelude::*;
+use bevy_pong::{config::BallConfig, game_entities::Ball};
+use proptest::prelude::*;
+
+const EPSILON: f32 = 0.01;
+
+fn ball() -> Ball {
+    Ball::from(&BallConfig::default())
+}
+
+fn velocity() -> impl Strategy<Value = Vec2> {
+    (-1.0e5_f32..1.0e5, -1.0e5_f32..1.0e5).prop_map(|(x, y)| Vec2::new(x, y))
+}
+
+proptest! {
+    #[test]
+    fn speed_never_exceeds_max(v in velocity()) {
+        let ball = ball();
+        let limited = ball.limit_velocity(v);
+        prop_assert!(limited.length() <= ball.max_speed + EPSILON);
+    }
+
+    #[test]
+    fn horizontal_speed_never_drops_below_min(v in velocity()) {
+        let ball = ball();
+        let limited = ball.limit_velocity(v);
+        prop_assert!(limited.x.abs() >= ball.min_horizontal_speed - EPSILON);
+    }
+
+    #[test]
+    fn direction_signs_are_kept(v in velocity()) {
+        let limited = ball().limit_velocity(v);
+        if v.x != 0.0 {
+            prop_assert_eq!(limited.x.signum(), v.x.signum());
+        }
+        prop_assert!(limited.y == 0.0 || limited.y.signum() == v.y.signum());
+    }
+
+    #[test]
+    fn valid_velocities_are_untouched(
+        x in 100.0_f32..1000.0,
+        y in -1000.0_f32..1000.0,
+        x_sign in prop::sample::select(vec![-1.0_f32, 1.0]),
+    ) {
+        let v = Vec2::new(x * x_sign, y);
+        prop_assert_eq!(ball().limit_velocity(v), v);
+    }
+}
+
+#[test]
+fn leftward_balls_are_limited_too() {
+    let ball = ball();
+    let limited = ball.limit_velocity(Vec2::new(-5000.0, -5000.0));
+    assert!((limited.length() - ball.max_speed).abs() < EPSILON);
+    assert!(limited.x < 0.0 && limited.y < 0.0);
+}
@@ -0,0 +1,60 @@
+use bevy::prelude::*;
+use bevy_pong::{
+    input::{Axis, AxisDirection, InputAxes, PlayerId},
+    input_bindings::*,
+};
+use std::path::Path;
+
+#[test]
+fn default_bindings_round_trip() {
+    let bindings = KeyBindings::default();
+    let ron = bindings.to_ron().unwrap();
+    assert_eq!(
+        KeyBindings::from_ron(Path::new("x"), &ron).unwrap(),
+        bindings
+    );
+}
+
+#[test]
+fn shipped_config_matches_defaults() {
+    let bindings = KeyBindings::load(Path::new(DEFAULT_BINDINGS_PATH)).unwrap();
+    assert_eq!(bindings, KeyBindings::default());
+}
+
+#[test]
+fn unknown_key_is_reported() {
+    let ron = "(players: [(player: One, axes: [(axis: Vertical, positive_keys: [Wx])])])";
+    let err = KeyBindings::from_ron(Path::new("bindings.ron"), ron).unwrap_err();
+    assert!(matches!(err, BindingsError::Parse { .. }));
+    let message = err.to_string();
+    assert!(message.contains("bindings.ron"), "{}", message);
+    assert!(message.contains("Wx"), "{}", message);
+}
+
+#[test]
+fn duplicate_player_is_rejected() {
+    let ron = "(players: [(player: One, axes: []), (player: One, axes: [])])";
+    let err = KeyBindings::from_ron(Path::new("bindings.ron"), ron).unwrap_err();
+    assert!(matches!(err, BindingsError::DuplicatePlayer(PlayerId::One)));
+}
+
+#[test]
+fn rebinding_moves_key_between_directions() {
+    let mut axes = InputAxes::from(&KeyBindings::default().players[0]);
+    axes.bind_key(Axis::Vertical, AxisDirection::Negative, KeyCode::W);
+    axes.bind_key(Axis::Vertical, AxisDirection::Positive, KeyCode::I);
+
+    let binding = &PlayerBindings::from(&axes).axes[0];
+    assert_eq!(binding.positive_keys, vec![KeyCode::I]);
+    assert_eq!(binding.negative_keys, vec![KeyCode::S, KeyCode::W]);
+}
+
+#[test]
+fn save_writes_loadable_file() {
+    let path = std::env::temp_dir().join("bevy_pong_bindings_test.ron");
+    let mut bindings = KeyBindings::default();
+    bindings.players[1].axes[0].deadzone = 0.3;
+    bindings.save(&path).unwrap();
+    assert_eq!(KeyBindings::load(&path).unwrap(), bindings);
+    std::fs::remove_file(path).unwrap();
+}
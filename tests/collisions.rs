@@ -0,0 +1,218 @@
+use bevy::{
+    ecs::event::{Events, ManualEventReader},
+    prelude::*,
+};
+use bevy_pong::{
+    config::PongConfig,
+    game_entities::*,
+    game_systems::{handle_ball_paddle_collision, set_waiting_ball_position},
+    headless,
+    input::PlayerId,
+    rng::GameRng,
+    trajectory::ArenaBounds,
+};
+use bevy_rapier2d::prelude::*;
+
+/// A two player match whose paddles stand still and whose own ball never launches, so only
+/// the balls a test spawns move.
+fn still_app() -> App {
+    still_app_with(PongConfig::default())
+}
+
+fn still_app_with(mut config: PongConfig) -> App {
+    config.ball.launch_delay = 1000.0;
+    let mut app = headless::headless_app();
+    app.insert_resource(config)
+        .insert_resource(MatchMode::TwoPlayer);
+    headless::run_ticks(&mut app, 1);
+    app
+}
+
+fn spawn_moving_ball(app: &mut App, position: Vec2, velocity: Vec2) -> Entity {
+    app.world
+        .resource_mut::<Events<SpawnBall>>()
+        .send(SpawnBall {
+            position,
+            velocity: Some(velocity),
+        });
+    headless::run_ticks(app, 1);
+    let mut balls = app.world.query::<(Entity, &Velocity, &Ball)>();
+    balls
+        .iter(&app.world)
+        .find(|x| x.1.linvel != Vec2::ZERO)
+        .unwrap()
+        .0
+}
+
+/// Runs `ticks` ticks and collects every `T` sent meanwhile.
+fn run_collecting<T: Clone + Send + Sync + 'static>(app: &mut App, ticks: u32) -> Vec<T> {
+    let mut reader = ManualEventReader::<T>::default();
+    let mut sent = Vec::new();
+    for _ in 0..ticks {
+        app.update();
+        let events = app.world.resource::<Events<T>>();
+        sent.extend(reader.iter(events).cloned());
+    }
+    sent
+}
+
+fn paddle_of(app: &mut App, player: PlayerId) -> Entity {
+    let mut paddles = app
+        .world
+        .query_filtered::<(Entity, &PlayerId), With<Paddle>>();
+    paddles.iter(&app.world).find(|x| *x.1 == player).unwrap().0
+}
+
+#[test]
+fn paddle_hits_are_sent_once_and_speed_up_the_ball_once() {
+    let mut app = still_app();
+    let paddle = paddle_of(&mut app, PlayerId::Two);
+    let ball = spawn_moving_ball(&mut app, Vec2::new(200.0, 0.0), Vec2::new(500.0, 0.0));
+
+    // back before it reaches the waiting ball in the middle
+    let hits = run_collecting::<BallHitPaddle>(&mut app, 45);
+    assert_eq!(hits.len(), 1);
+    assert_eq!((hits[0].ball, hits[0].paddle), (ball, paddle));
+    assert_eq!(app.world.get::<LastHitBy>(ball).unwrap().0, Some(paddle));
+    // straight into the paddle's center, so it comes back straight and only faster
+    let velocity = app.world.get::<Velocity>(ball).unwrap().linvel;
+    let multiplier = PongConfig::default().ball.speed_multiplier;
+    assert!(
+        (velocity.x + 500.0 * multiplier).abs() < 1.0,
+        "{}",
+        velocity
+    );
+}
+
+#[test]
+fn wall_bounces_are_sent_once() {
+    let mut app = still_app();
+    let ball = spawn_moving_ball(&mut app, Vec2::new(-200.0, 300.0), Vec2::new(150.0, 600.0));
+
+    let bounces = run_collecting::<BallHitWall>(&mut app, 40);
+    assert_eq!(bounces.len(), 1);
+    assert_eq!(bounces[0].ball, ball);
+    assert!(app.world.get::<Velocity>(ball).unwrap().linvel.y < 0.0);
+}
+
+#[test]
+fn goals_are_entered_and_scored_once() {
+    let mut app = still_app();
+    // past the paddle, with nothing between the ball and the AI's goal
+    let ball = spawn_moving_ball(&mut app, Vec2::new(600.0, 400.0), Vec2::new(500.0, 0.0));
+
+    let entered = run_collecting::<BallEnteredGoal>(&mut app, 60);
+    assert_eq!(entered.len(), 1);
+    assert_eq!(entered[0].ball, ball);
+    let goal = app.world.get::<Goal>(entered[0].goal).unwrap();
+    assert_eq!(goal.team, Team::AI);
+
+    let mut scores = app.world.query::<&MatchScore>();
+    let score = scores.iter(&app.world).next().unwrap();
+    assert_eq!(score.score[&Team::Player], 1);
+    assert_eq!(score.score[&Team::AI], 0);
+}
+
+#[test]
+fn goal_posts_keep_balls_out_beside_the_mouth() {
+    let mut config = PongConfig::default();
+    config.arena.goal_height = 300.0;
+    let mut app = still_app_with(config);
+    // into the post above the mouth
+    let ball = spawn_moving_ball(&mut app, Vec2::new(600.0, 400.0), Vec2::new(500.0, 0.0));
+
+    let bounces = run_collecting::<BallHitWall>(&mut app, 60);
+    assert_eq!(bounces.len(), 1);
+    assert!(app.world.get::<Velocity>(ball).unwrap().linvel.x < 0.0);
+    let mut scores = app.world.query::<&MatchScore>();
+    assert_eq!(
+        scores.iter(&app.world).next().unwrap().score[&Team::Player],
+        0
+    );
+
+    app.world.get_mut::<Transform>(ball).unwrap().translation = Vec3::new(600.0, 100.0, 0.0);
+    app.world.get_mut::<Velocity>(ball).unwrap().linvel = Vec2::new(500.0, 0.0);
+    let entered = run_collecting::<BallEnteredGoal>(&mut app, 60);
+    assert_eq!(entered.len(), 1);
+    let mut scores = app.world.query::<&MatchScore>();
+    assert_eq!(
+        scores.iter(&app.world).next().unwrap().score[&Team::Player],
+        1
+    );
+}
+
+#[test]
+fn moving_paddles_bend_and_spin_the_ball() {
+    let mut config = PongConfig::default();
+    config.paddle.bounce_noise = 0.0;
+    let ball = Ball::from(&config.ball);
+    let paddle_transform = Transform::from_xyz(460.0, 0.0, 0.0);
+    let paddle_collider = Collider::cuboid(25.0, 125.0);
+    let ball_transform = Transform::from_xyz(422.5, 0.0, 0.0);
+    let ball_collider = Collider::ball(config.ball.radius);
+    let bounce = |paddle_velocity: Vec2| {
+        // already reflected by the physics step
+        let mut velocity = Velocity::linear(Vec2::new(-500.0, 0.0));
+        handle_ball_paddle_collision(
+            &paddle_transform,
+            &Velocity::linear(paddle_velocity),
+            &paddle_collider,
+            &ball_transform,
+            &mut velocity,
+            &ball_collider,
+            &ball,
+            &config.paddle,
+            &mut GameRng::new(headless::SEED),
+        );
+        velocity
+    };
+
+    let still = bounce(Vec2::ZERO);
+    assert_eq!(still.linvel.y, 0.0);
+    assert_eq!(still.angvel, 0.0);
+
+    let up = bounce(Vec2::new(0.0, 500.0));
+    let down = bounce(Vec2::new(0.0, -500.0));
+    assert!(up.linvel.y > 0.0 && down.linvel.y < 0.0);
+    // english changes where the ball goes, not how fast
+    assert!((up.linvel.length() - still.linvel.length()).abs() < 0.01);
+    assert!(up.angvel > 0.0 && down.angvel < 0.0);
+    assert_eq!(up.angvel, -down.angvel);
+}
+
+#[test]
+fn spinning_balls_curve_at_the_same_speed() {
+    let mut app = still_app();
+    let ball = spawn_moving_ball(&mut app, Vec2::new(0.0, -200.0), Vec2::new(-400.0, 0.0));
+    app.world.get_mut::<Velocity>(ball).unwrap().angvel = 20.0;
+
+    headless::run_ticks(&mut app, 30);
+    let velocity = *app.world.get::<Velocity>(ball).unwrap();
+    // counterclockwise spin turns a leftward ball downwards
+    assert!(velocity.linvel.y < -10.0, "{}", velocity.linvel);
+    assert!((velocity.linvel.length() - 400.0).abs() < 1.0);
+    assert!(velocity.angvel > 0.0 && velocity.angvel < 20.0);
+}
+
+#[test]
+fn waiting_balls_stay_clear_of_each_other() {
+    let bounds = ArenaBounds {
+        min: Vec2::new(-400.0, -300.0),
+        max: Vec2::new(400.0, 300.0),
+    };
+    let radius = 10.0;
+    let positions: Vec<_> = (0..90)
+        .map(|slot| {
+            let mut transform = Transform::default();
+            set_waiting_ball_position(&mut transform, slot, radius, &bounds);
+            transform.translation.truncate()
+        })
+        .collect();
+    assert_eq!(positions[0], Vec2::ZERO);
+    for (i, a) in positions.iter().enumerate() {
+        assert!(a.abs().cmple(bounds.max - radius).all(), "{}", a);
+        for b in &positions[i + 1..] {
+            assert!(a.distance(*b) > radius * 2.0, "{} and {}", a, b);
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use bevy::prelude::*;
+use bevy_pong::{
+    config::{ConfigError, PongConfig},
+    game_entities::*,
+    headless,
+};
+use bevy_rapier2d::prelude::*;
+use std::fs;
+
+#[test]
+fn shipped_config_matches_defaults() {
+    let contents = fs::read("assets/pong.config.ron").unwrap();
+    assert_eq!(
+        PongConfig::from_ron(&contents).unwrap(),
+        PongConfig::default()
+    );
+}
+
+#[test]
+fn invalid_values_are_rejected() {
+    let err = PongConfig::from_ron(b"(ball: (initial_speed: 3000.0))").unwrap_err();
+    assert!(matches!(
+        err,
+        ConfigError::InvalidValue {
+            field: "ball.max_speed",
+            ..
+        }
+    ));
+
+    let err = PongConfig::from_ron(b"(paddle: (max_bounce_angle: 90.0))").unwrap_err();
+    assert!(matches!(
+        err,
+        ConfigError::InvalidValue {
+            field: "paddle.max_bounce_angle",
+            ..
+        }
+    ));
+
+    let err = PongConfig::from_ron(b"(ball: (spin_decay: 2.0))").unwrap_err();
+    assert!(matches!(
+        err,
+        ConfigError::InvalidValue {
+            field: "ball.spin_decay",
+            ..
+        }
+    ));
+
+    let err = PongConfig::from_ron(b"(audio: (volume: 1.5))").unwrap_err();
+    assert!(matches!(
+        err,
+        ConfigError::InvalidValue {
+            field: "audio.volume",
+            ..
+        }
+    ));
+
+    assert!(matches!(
+        PongConfig::from_ron(b"(paddle: ("),
+        Err(ConfigError::Parse(_))
+    ));
+}
+
+#[test]
+fn config_changes_apply_to_spawned_entities() {
+    let mut app = headless::headless_app();
+    headless::run_ticks(&mut app, 1);
+    {
+        let mut config = app.world.resource_mut::<PongConfig>();
+        config.paddle.speed = 800.0;
+        config.paddle.size = Vec2::new(40.0, 300.0);
+        config.ball.radius = 20.0;
+        config.arena.width = 1600.0;
+    }
+    headless::run_ticks(&mut app, 1);
+
+    assert_eq!(app.world.resource::<Arena>().width, 1600.0);
+    let mut paddles = app.world.query::<(&Paddle, &Sprite)>();
+    for (paddle, sprite) in paddles.iter(&app.world) {
+        assert_eq!(paddle.speed, 800.0);
+        assert_eq!(sprite.custom_size, Some(Vec2::new(40.0, 300.0)));
+    }
+    let mut balls = app.world.query_filtered::<&Collider, With<Ball>>();
+    for collider in balls.iter(&app.world) {
+        assert_eq!(collider.as_ball().unwrap().radius(), 20.0);
+    }
+}
@@ -0,0 +1,108 @@
+use bevy::prelude::*;
+use bevy::{ecs::event::Events, input::gamepad::GamepadEventRaw};
+use bevy_pong::{game_entities::*, headless, input};
+use bevy_rapier2d::prelude::*;
+
+fn send(app: &mut App, gamepad: usize, event: GamepadEventType) {
+    app.world
+        .resource_mut::<Events<GamepadEventRaw>>()
+        .send(GamepadEventRaw(Gamepad(gamepad), event));
+    app.update();
+}
+
+fn assigned_gamepad(app: &mut App, player: input::PlayerId) -> Option<Gamepad> {
+    let mut query = app.world.query::<&input::InputAxes>();
+    query
+        .iter(&app.world)
+        .find(|x| x.player == player)
+        .unwrap()
+        .gamepad
+}
+
+fn vertical_input(app: &mut App, player: input::PlayerId) -> f32 {
+    let mut query = app.world.query::<&input::InputAxes>();
+    query
+        .iter(&app.world)
+        .find(|x| x.player == player)
+        .unwrap()
+        .val[&input::Axis::Vertical]
+        .val
+}
+
+fn player_paddle_velocity(app: &mut App) -> f32 {
+    let mut query = app
+        .world
+        .query_filtered::<&Velocity, (With<PlayerPaddle>, With<Paddle>)>();
+    query.iter(&app.world).next().unwrap().linvel.y
+}
+
+#[test]
+fn gamepads_are_assigned_on_connect_and_freed_on_disconnect() {
+    let mut app = headless::headless_app();
+    app.update();
+    assert_eq!(assigned_gamepad(&mut app, input::PlayerId::One), None);
+
+    send(&mut app, 3, GamepadEventType::Connected);
+    send(&mut app, 7, GamepadEventType::Connected);
+    assert_eq!(
+        assigned_gamepad(&mut app, input::PlayerId::One),
+        Some(Gamepad(3))
+    );
+    assert_eq!(
+        assigned_gamepad(&mut app, input::PlayerId::Two),
+        Some(Gamepad(7))
+    );
+
+    send(&mut app, 3, GamepadEventType::Disconnected);
+    assert_eq!(assigned_gamepad(&mut app, input::PlayerId::One), None);
+
+    send(&mut app, 5, GamepadEventType::Connected);
+    assert_eq!(
+        assigned_gamepad(&mut app, input::PlayerId::One),
+        Some(Gamepad(5))
+    );
+}
+
+#[test]
+fn stick_moves_paddle_outside_deadzone() {
+    let mut app = headless::headless_app();
+    app.update();
+    send(&mut app, 0, GamepadEventType::Connected);
+
+    send(
+        &mut app,
+        0,
+        GamepadEventType::AxisChanged(GamepadAxisType::LeftStickY, 1.0),
+    );
+    assert_eq!(vertical_input(&mut app, input::PlayerId::One), 1.0);
+    assert!(player_paddle_velocity(&mut app) > 0.0);
+
+    send(
+        &mut app,
+        0,
+        GamepadEventType::AxisChanged(GamepadAxisType::LeftStickY, 0.1),
+    );
+    assert_eq!(vertical_input(&mut app, input::PlayerId::One), 0.0);
+    assert_eq!(player_paddle_velocity(&mut app), 0.0);
+}
+
+#[test]
+fn dpad_buttons_drive_axis() {
+    let mut app = headless::headless_app();
+    app.update();
+    send(&mut app, 0, GamepadEventType::Connected);
+
+    send(
+        &mut app,
+        0,
+        GamepadEventType::ButtonChanged(GamepadButtonType::DPadDown, 1.0),
+    );
+    assert_eq!(vertical_input(&mut app, input::PlayerId::One), -1.0);
+
+    send(
+        &mut app,
+        0,
+        GamepadEventType::ButtonChanged(GamepadButtonType::DPadDown, 0.0),
+    );
+    assert_eq!(vertical_input(&mut app, input::PlayerId::One), 0.0);
+}
@@ -0,0 +1,111 @@
+use bevy::{ecs::event::Events, prelude::*};
+use bevy_pong::{game_entities::*, headless, input::PlayerId, trajectory::ArenaBounds};
+use bevy_rapier2d::prelude::*;
+
+#[test]
+fn ball_launches_after_delay() {
+    let mut app = headless::headless_app();
+    // the launch delay is 0.5s, so the ball is still waiting after a handful of ticks
+    headless::run_ticks(&mut app, 10);
+    let mut balls = app.world.query_filtered::<&Velocity, With<Ball>>();
+    assert_eq!(balls.iter(&app.world).next().unwrap().linvel, Vec2::ZERO);
+
+    headless::run_ticks(&mut app, 60);
+    let mut balls = app
+        .world
+        .query_filtered::<(&Velocity, &Transform), With<Ball>>();
+    let (velocity, transform) = balls.iter(&app.world).next().unwrap();
+    assert_ne!(velocity.linvel.x, 0.0);
+    assert_ne!(transform.translation.x, 0.0);
+}
+
+#[test]
+fn clock_advances_one_tick_per_update() {
+    let mut app = headless::headless_app();
+    headless::run_ticks(&mut app, 120);
+    let clock = app.world.resource::<GameClock>();
+    assert_eq!(clock.tick, 120);
+    assert!((clock.elapsed.as_secs_f64() - 2.0).abs() < 1e-6);
+}
+
+#[test]
+fn spawned_balls_launch_independently() {
+    let mut app = headless::headless_app();
+    headless::run_ticks(&mut app, 1);
+    app.world
+        .resource_mut::<Events<SpawnBall>>()
+        .send(SpawnBall {
+            position: Vec2::new(0.0, 200.0),
+            velocity: Some(Vec2::new(400.0, 0.0)),
+        });
+    headless::run_ticks(&mut app, 5);
+
+    let mut balls = app
+        .world
+        .query_filtered::<(Entity, &Velocity, &Transform), With<Ball>>();
+    let balls: Vec<_> = balls.iter(&app.world).collect();
+    assert_eq!(balls.len(), 2);
+    // the spawned ball is already moving while the original one still waits for its delay
+    let (spawned, _, _) = *balls.iter().find(|x| x.1.linvel.x > 0.0).unwrap();
+    assert!(balls.iter().any(|x| x.1.linvel == Vec2::ZERO));
+
+    // the spawned ball heads for the AI paddle, so it becomes the AI's target
+    let mut ai = app.world.query::<&AIPaddle>();
+    assert_eq!(
+        ai.iter(&app.world).next().unwrap().target_ball,
+        Some(spawned)
+    );
+}
+
+#[test]
+fn layout_follows_arena_size() {
+    let mut app = headless::headless_app();
+    headless::run_ticks(&mut app, 1);
+    app.world.resource_mut::<Arena>().width = 1280.0;
+    headless::run_ticks(&mut app, 1);
+
+    let arena = app.world.resource::<Arena>().clone();
+    assert_eq!(*app.world.resource::<ArenaBounds>(), arena.bounds());
+    let mut paddles = app.world.query::<(&PlayerId, &Transform)>();
+    for (player, transform) in paddles.iter(&app.world) {
+        assert_eq!(transform.translation.x, arena.paddle_x(*player));
+    }
+    let mut walls = app
+        .world
+        .query::<(&Bounds, Option<&GoalPost>, &Transform)>();
+    for (bounds, post, transform) in walls.iter(&app.world) {
+        let expected = match post {
+            Some(post) => arena.goal_post(bounds.side, post.end),
+            None => arena.wall(bounds.side),
+        };
+        assert_eq!(transform.translation.truncate(), expected.1);
+    }
+    let mut goals = app.world.query::<(&Goal, &Transform)>();
+    for (goal, transform) in goals.iter(&app.world) {
+        assert_eq!(
+            transform.translation.truncate(),
+            arena.goal_zone(goal.side).1
+        );
+    }
+}
+
+fn ball_states(app: &mut App) -> Vec<(Vec2, Vec2)> {
+    let mut balls = app
+        .world
+        .query_filtered::<(&Transform, &Velocity), With<Ball>>();
+    balls
+        .iter(&app.world)
+        .map(|(transform, velocity)| (transform.translation.truncate(), velocity.linvel))
+        .collect()
+}
+
+#[test]
+fn same_seed_plays_out_identically() {
+    let mut a = headless::headless_app();
+    let mut b = headless::headless_app();
+    for _ in 0..600 {
+        a.update();
+        b.update();
+        assert_eq!(ball_states(&mut a), ball_states(&mut b));
+    }
+}
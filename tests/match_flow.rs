@@ -0,0 +1,131 @@
+use bevy::{
+    ecs::event::{Events, ManualEventReader},
+    input::{keyboard::KeyboardInput, ElementState},
+    prelude::*,
+};
+use bevy_pong::{game_entities::*, headless, input::PlayerId};
+use iyes_loopless::prelude::*;
+
+/// Presses and releases `key` the way a keyboard does, so systems see it as just pressed.
+fn tap(app: &mut App, key: KeyCode) {
+    for state in [ElementState::Pressed, ElementState::Released] {
+        app.world
+            .resource_mut::<Events<KeyboardInput>>()
+            .send(KeyboardInput {
+                scan_code: 0,
+                key_code: Some(key),
+                state,
+            });
+        app.update();
+    }
+}
+
+fn state(app: &App) -> GameState {
+    app.world.resource::<CurrentState<GameState>>().0
+}
+
+fn score_goals(app: &mut App, team: Team, goals: u32) {
+    let mut balls = app.world.query_filtered::<Entity, With<Ball>>();
+    let ball = balls.iter(&app.world).next().unwrap();
+    for _ in 0..goals {
+        app.world
+            .resource_mut::<Events<GoalEvent>>()
+            .send(GoalEvent {
+                ball,
+                team: team.clone(),
+            });
+        app.update();
+    }
+}
+
+fn score(app: &mut App, team: Team) -> i32 {
+    let mut scores = app.world.query::<&MatchScore>();
+    scores.iter(&app.world).next().unwrap().score[&team]
+}
+
+#[test]
+fn winning_ends_the_match_and_a_rematch_starts_from_zero() {
+    let mut app = headless::headless_app();
+    app.insert_resource(WinCondition {
+        target_score: 3,
+        win_by: 2,
+    });
+    headless::run_ticks(&mut app, 1);
+
+    let mut won = ManualEventReader::<MatchWon>::default();
+    score_goals(&mut app, Team::AI, 2);
+    score_goals(&mut app, Team::Player, 3);
+    // 3 to 2 isn't far enough ahead
+    assert_eq!(state(&app), GameState::Playing);
+    score_goals(&mut app, Team::Player, 1);
+    app.update();
+    assert_eq!(state(&app), GameState::GameOver);
+    let won: Vec<_> = won
+        .iter(app.world.resource::<Events<MatchWon>>())
+        .map(|x| x.team.clone())
+        .collect();
+    assert_eq!(won, [Team::Player]);
+
+    tap(&mut app, KeyCode::Space);
+    assert_eq!(state(&app), GameState::Playing);
+    assert_eq!(score(&mut app, Team::Player), 0);
+    assert_eq!(score(&mut app, Team::AI), 0);
+}
+
+#[test]
+fn pausing_freezes_the_match() {
+    let mut app = headless::headless_app();
+    headless::run_ticks(&mut app, 60);
+    tap(&mut app, KeyCode::Escape);
+    assert_eq!(state(&app), GameState::Paused);
+
+    let mut balls = app.world.query_filtered::<&Transform, With<Ball>>();
+    let before = balls.iter(&app.world).next().unwrap().translation;
+    headless::run_ticks(&mut app, 30);
+    let mut balls = app.world.query_filtered::<&Transform, With<Ball>>();
+    assert_eq!(balls.iter(&app.world).next().unwrap().translation, before);
+
+    tap(&mut app, KeyCode::Escape);
+    assert_eq!(state(&app), GameState::Playing);
+    headless::run_ticks(&mut app, 5);
+    let mut balls = app.world.query_filtered::<&Transform, With<Ball>>();
+    assert_ne!(balls.iter(&app.world).next().unwrap().translation, before);
+}
+
+#[test]
+fn main_menu_starts_a_fresh_match() {
+    let mut app = headless::headless_app();
+    headless::run_ticks(&mut app, 1);
+    score_goals(&mut app, Team::Player, 1);
+    tap(&mut app, KeyCode::Escape);
+    tap(&mut app, KeyCode::M);
+    assert_eq!(state(&app), GameState::MainMenu);
+
+    tap(&mut app, KeyCode::Space);
+    assert_eq!(state(&app), GameState::Playing);
+    assert_eq!(score(&mut app, Team::Player), 0);
+}
+
+#[test]
+fn main_menu_picks_the_match_mode() {
+    let mut app = headless::headless_app();
+    headless::run_ticks(&mut app, 1);
+    tap(&mut app, KeyCode::Escape);
+    tap(&mut app, KeyCode::M);
+    assert_eq!(state(&app), GameState::MainMenu);
+
+    tap(&mut app, KeyCode::Key2);
+    assert_eq!(state(&app), GameState::Playing);
+    assert_eq!(*app.world.resource::<MatchMode>(), MatchMode::TwoPlayer);
+    let mut paddles = app
+        .world
+        .query_filtered::<&PlayerId, (With<Paddle>, With<PlayerPaddle>)>();
+    assert_eq!(paddles.iter(&app.world).count(), 2);
+
+    tap(&mut app, KeyCode::Escape);
+    tap(&mut app, KeyCode::M);
+    tap(&mut app, KeyCode::Key1);
+    assert_eq!(*app.world.resource::<MatchMode>(), MatchMode::VsAI);
+    let mut ai = app.world.query_filtered::<&PlayerId, With<AIPaddle>>();
+    assert_eq!(ai.iter(&app.world).collect::<Vec<_>>(), [&PlayerId::Two]);
+}
@@ -0,0 +1,156 @@
+use bevy::prelude::*;
+use bevy_pong::{
+    game_entities::*,
+    headless,
+    input::PlayerId,
+    net::{NetConfig, NetSession},
+};
+use bevy_rapier2d::prelude::*;
+use iyes_loopless::prelude::*;
+use std::net::UdpSocket;
+
+const FRAMES: u32 = 600;
+
+fn peer_app(socket: UdpSocket, peer: &UdpSocket, player: PlayerId, seed: u64) -> App {
+    let session = NetSession::new(socket, peer.local_addr().unwrap(), player, seed)
+        .unwrap()
+        // no delay, so every change of input is predicted wrong at first
+        .with_config(NetConfig {
+            input_delay: 0,
+            max_prediction: 8,
+        });
+    let mut app = headless::headless_app();
+    app.insert_resource(session);
+    app
+}
+
+fn hold_key(app: &mut App, key: KeyCode, pressed: bool) {
+    let mut keys = app.world.resource_mut::<Input<KeyCode>>();
+    if pressed {
+        keys.press(key);
+    } else {
+        keys.release(key);
+    }
+}
+
+fn paddle_heights(app: &mut App) -> Vec<f32> {
+    let mut query = app
+        .world
+        .query_filtered::<(Entity, &Transform), With<Paddle>>();
+    let mut paddles: Vec<_> = query.iter(&app.world).collect();
+    paddles.sort_by_key(|x| x.0);
+    paddles.iter().map(|(_, x)| x.translation.y).collect()
+}
+
+fn paddle_height(app: &mut App, player: PlayerId) -> f32 {
+    let mut query = app
+        .world
+        .query_filtered::<(&PlayerId, &Transform), With<Paddle>>();
+    let (_, transform) = query.iter(&app.world).find(|x| *x.0 == player).unwrap();
+    transform.translation.y
+}
+
+#[test]
+fn peers_on_localhost_play_the_same_match() {
+    let socket_one = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let socket_two = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let mut one = peer_app(
+        socket_one.try_clone().unwrap(),
+        &socket_two,
+        PlayerId::One,
+        7,
+    );
+    let mut two = peer_app(socket_two, &socket_one, PlayerId::Two, 1234);
+
+    for frame in 0..FRAMES {
+        // both peers steer with their own player one keys
+        hold_key(&mut one, KeyCode::W, (frame / 30) % 2 == 0);
+        hold_key(&mut two, KeyCode::W, (frame / 45) % 3 == 1);
+        one.update();
+        two.update();
+    }
+
+    let mut rollbacks = 0;
+    for app in [&mut one, &mut two] {
+        assert_eq!(
+            app.world.resource::<CurrentState<GameState>>().0,
+            GameState::Playing
+        );
+        let session = app.world.resource::<NetSession>();
+        assert!(session.is_running());
+        let stats = session.stats();
+        rollbacks += stats.rollbacks;
+        assert!(stats.verified_ticks > FRAMES / 2, "{:?}", stats);
+        assert_eq!(stats.desyncs, 0);
+    }
+    // whichever peer runs ahead predicts its peer's input
+    assert!(rollbacks > 0);
+    assert!(paddle_heights(&mut one).iter().all(|y| *y != 0.0));
+
+    // peer one keeps changing its mind, so peer two rolls back over and over, and the ticks
+    // it resimulates mustn't leak peer one's input into its own
+    hold_key(&mut two, KeyCode::W, true);
+    let rollbacks = two.world.resource::<NetSession>().stats().rollbacks;
+    let mut height = paddle_height(&mut two, PlayerId::Two);
+    for frame in 0..120 {
+        hold_key(&mut one, KeyCode::W, (frame / 3) % 2 == 0);
+        hold_key(&mut one, KeyCode::S, (frame / 3) % 2 == 1);
+        one.update();
+        two.update();
+        let next = paddle_height(&mut two, PlayerId::Two);
+        assert!(next >= height, "frame {}: {} after {}", frame, next, height);
+        height = next;
+    }
+    assert!(height > 0.0);
+    assert!(two.world.resource::<NetSession>().stats().rollbacks > rollbacks);
+}
+
+fn inputs_packet(inputs: &[f32]) -> Vec<u8> {
+    let mut bytes = b"PN1\x01".to_vec();
+    bytes.extend_from_slice(&0u32.to_le_bytes());
+    bytes.extend_from_slice(&0u32.to_le_bytes());
+    bytes.push(inputs.len() as u8);
+    for input in inputs {
+        bytes.extend_from_slice(&input.to_le_bytes());
+    }
+    bytes.push(0);
+    bytes
+}
+
+#[test]
+fn malformed_inputs_from_the_peer_are_dropped() {
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let peer = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let address = socket.local_addr().unwrap();
+    let mut app = peer_app(socket, &peer, PlayerId::One, 7);
+
+    let mut hello = b"PN1\x00\x01".to_vec();
+    hello.extend_from_slice(&0u64.to_le_bytes());
+    peer.send_to(&hello, address).unwrap();
+    for _ in 0..10 {
+        app.update();
+    }
+    assert!(app.world.resource::<NetSession>().is_running());
+
+    let truncated = inputs_packet(&[-1.0]);
+    peer.send_to(&truncated[..truncated.len() - 1], address)
+        .unwrap();
+    peer.send_to(&inputs_packet(&[f32::NAN]), address).unwrap();
+    peer.send_to(&inputs_packet(&[f32::INFINITY]), address)
+        .unwrap();
+    // only this one arrives in one piece, and steers like a full press
+    peer.send_to(&inputs_packet(&[5.0]), address).unwrap();
+    for _ in 0..5 {
+        app.update();
+    }
+
+    let mut paddles = app
+        .world
+        .query::<(&PlayerId, &Paddle, &Transform, &Velocity)>();
+    let (_, paddle, transform, velocity) = paddles
+        .iter(&app.world)
+        .find(|x| *x.0 == PlayerId::Two)
+        .unwrap();
+    assert!(transform.translation.y > 0.0);
+    assert!(velocity.linvel.y <= paddle.speed);
+}
@@ -0,0 +1,183 @@
+use bevy::prelude::*;
+use bevy_pong::{
+    config::{PaddleConfig, PongConfig},
+    game_entities::*,
+    headless,
+    input::PlayerId,
+    trajectory::ArenaBounds,
+};
+use bevy_rapier2d::prelude::*;
+
+fn assert_paddles_inside(app: &mut App) {
+    let arena = *app.world.resource::<ArenaBounds>();
+    let mut paddles = app
+        .world
+        .query_filtered::<(&Transform, &Collider), With<Paddle>>();
+    for (transform, collider) in paddles.iter(&app.world) {
+        let half_height = collider.as_cuboid().unwrap().half_extents().y;
+        let y = transform.translation.y;
+        assert!(
+            y + half_height <= arena.max.y + 0.01,
+            "paddle at {} left the top",
+            y
+        );
+        assert!(
+            y - half_height >= arena.min.y - 0.01,
+            "paddle at {} left the bottom",
+            y
+        );
+    }
+}
+
+fn player_y(app: &mut App) -> f32 {
+    let mut paddles = app.world.query::<(&PlayerId, &Transform)>();
+    paddles
+        .iter(&app.world)
+        .find(|x| *x.0 == PlayerId::One)
+        .unwrap()
+        .1
+        .translation
+        .y
+}
+
+#[test]
+fn paddles_stay_inside_at_extreme_speeds() {
+    let mut app = headless::headless_app();
+    // one tick at this speed crosses the arena many times over
+    let mut config = app.world.resource_mut::<PongConfig>();
+    config.paddle.speed = 1_000_000.0;
+    config.paddle.acceleration = f32::MAX;
+    config.paddle.deceleration = f32::MAX;
+
+    for key in [KeyCode::W, KeyCode::S, KeyCode::W] {
+        app.world.resource_mut::<Input<KeyCode>>().press(key);
+        for _ in 0..30 {
+            app.update();
+            assert_paddles_inside(&mut app);
+        }
+        app.world.resource_mut::<Input<KeyCode>>().release(key);
+    }
+
+    // the paddle still reaches the wall instead of stopping short of it
+    let arena = *app.world.resource::<ArenaBounds>();
+    let half_height = app.world.resource::<PongConfig>().paddle.size.y / 2.0;
+    assert!((player_y(&mut app) - (arena.max.y - half_height)).abs() < 0.01);
+}
+
+#[test]
+fn growing_paddle_is_pushed_back_inside() {
+    let mut app = headless::headless_app();
+    app.world.resource_mut::<Input<KeyCode>>().press(KeyCode::W);
+    headless::run_ticks(&mut app, 120);
+    app.world.resource_mut::<PongConfig>().paddle.size.y = 600.0;
+    headless::run_ticks(&mut app, 2);
+    assert_paddles_inside(&mut app);
+}
+
+fn player_velocity(app: &mut App) -> f32 {
+    paddle_velocity(app, PlayerId::One)
+}
+
+fn paddle_velocity(app: &mut App, player: PlayerId) -> f32 {
+    let mut paddles = app.world.query::<(&PlayerId, &Velocity)>();
+    paddles
+        .iter(&app.world)
+        .find(|x| *x.0 == player)
+        .unwrap()
+        .1
+        .linvel
+        .y
+}
+
+#[test]
+fn paddles_speed_up_and_slow_down_gradually() {
+    let mut app = headless::headless_app();
+    headless::run_ticks(&mut app, 1);
+    let config = app.world.resource::<PongConfig>().paddle.clone();
+    let tick = 1.0 / TICK_RATE as f32;
+
+    app.world.resource_mut::<Input<KeyCode>>().press(KeyCode::W);
+    headless::run_ticks(&mut app, 1);
+    assert!((player_velocity(&mut app) - config.acceleration * tick).abs() < 0.01);
+    headless::run_ticks(&mut app, 10);
+    assert_eq!(player_velocity(&mut app), config.speed);
+
+    // turning around brakes first
+    app.world
+        .resource_mut::<Input<KeyCode>>()
+        .release(KeyCode::W);
+    app.world.resource_mut::<Input<KeyCode>>().press(KeyCode::S);
+    headless::run_ticks(&mut app, 1);
+    let velocity = player_velocity(&mut app);
+    assert!((velocity - (config.speed - config.deceleration * tick)).abs() < 0.01);
+}
+
+#[test]
+fn double_taps_dash_once_per_cooldown() {
+    let paddle = Paddle::from(&PaddleConfig {
+        dash_speed: 2000.0,
+        ..default()
+    });
+    let tick = 1.0 / TICK_RATE as f32;
+    let mut motion = PaddleMotion::default();
+    let mut velocity = 0.0;
+    let mut run = |input: f32, ticks: u32| {
+        for _ in 0..ticks {
+            velocity = motion.step(&paddle, velocity, input, tick);
+        }
+        velocity
+    };
+
+    run(1.0, 3);
+    run(0.0, 3);
+    assert_eq!(run(1.0, 1), paddle.dash_speed);
+    // back to the normal top speed once the dash is over
+    let after_dash = run(1.0, (paddle.dash_duration / tick) as u32 + 30);
+    assert_eq!(after_dash, paddle.speed);
+
+    // still cooling down
+    run(0.0, 3);
+    run(1.0, 3);
+    run(0.0, 3);
+    assert!(run(1.0, 1) <= paddle.speed);
+}
+
+#[test]
+fn two_players_steer_their_own_paddles() {
+    let mut app = headless::headless_app();
+    app.insert_resource(MatchMode::TwoPlayer);
+    headless::run_ticks(&mut app, 1);
+
+    app.world.resource_mut::<Input<KeyCode>>().press(KeyCode::W);
+    app.world
+        .resource_mut::<Input<KeyCode>>()
+        .press(KeyCode::Down);
+    headless::run_ticks(&mut app, 20);
+    assert!(paddle_velocity(&mut app, PlayerId::One) > 0.0);
+    assert!(paddle_velocity(&mut app, PlayerId::Two) < 0.0);
+
+    // player two's keys leave player one alone
+    app.world
+        .resource_mut::<Input<KeyCode>>()
+        .release(KeyCode::W);
+    headless::run_ticks(&mut app, 20);
+    assert_eq!(paddle_velocity(&mut app, PlayerId::One), 0.0);
+}
+
+#[test]
+fn arrows_steer_player_one_against_the_ai() {
+    let mut app = headless::headless_app();
+    headless::run_ticks(&mut app, 1);
+
+    app.world
+        .resource_mut::<Input<KeyCode>>()
+        .press(KeyCode::Up);
+    headless::run_ticks(&mut app, 20);
+    assert!(player_velocity(&mut app) > 0.0);
+
+    // both sets of keys together are no faster
+    app.world.resource_mut::<Input<KeyCode>>().press(KeyCode::W);
+    headless::run_ticks(&mut app, 20);
+    let speed = app.world.resource::<PongConfig>().paddle.speed;
+    assert!(player_velocity(&mut app) <= speed);
+}
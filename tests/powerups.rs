@@ -0,0 +1,189 @@
+use bevy::{ecs::event::Events, prelude::*};
+use bevy_pong::{config::PongConfig, game_entities::*, headless, input::PlayerId, powerups::*};
+use bevy_rapier2d::prelude::*;
+
+fn player_paddle(app: &mut App) -> Entity {
+    let mut paddles = app.world.query::<(Entity, &PlayerId)>();
+    paddles
+        .iter(&app.world)
+        .find(|x| *x.1 == PlayerId::One)
+        .unwrap()
+        .0
+}
+
+fn first_ball(app: &mut App) -> Entity {
+    let mut balls = app.world.query_filtered::<Entity, With<Ball>>();
+    balls.iter(&app.world).next().unwrap()
+}
+
+fn collect(app: &mut App, kind: PowerUpKind) {
+    let paddle = player_paddle(app);
+    let ball = first_ball(app);
+    app.world
+        .resource_mut::<Events<PowerUpCollected>>()
+        .send(PowerUpCollected { kind, paddle, ball });
+}
+
+fn effects_on(app: &App, target: Entity) -> usize {
+    app.world.get::<ActiveEffects>(target).unwrap().0.len()
+}
+
+fn paddle_height(app: &mut App) -> f32 {
+    let paddle = player_paddle(app);
+    let sprite = app.world.get::<Sprite>(paddle).unwrap();
+    sprite.custom_size.unwrap().y
+}
+
+#[test]
+fn paddle_size_is_restored_on_expiry() {
+    let mut app = headless::headless_app();
+    headless::run_ticks(&mut app, 1);
+    let original = paddle_height(&mut app);
+
+    collect(&mut app, PowerUpKind::BiggerPaddle);
+    headless::run_ticks(&mut app, 2);
+    assert!(paddle_height(&mut app) > original);
+
+    // an opposing effect replaces the running one instead of stacking on top of it
+    collect(&mut app, PowerUpKind::SmallerPaddle);
+    headless::run_ticks(&mut app, 2);
+    assert!(paddle_height(&mut app) < original);
+    let paddle = player_paddle(&mut app);
+    assert_eq!(effects_on(&app, paddle), 1);
+
+    let ticks = (PowerUpKind::SmallerPaddle.duration() * headless::TICK_RATE as f32) as u32;
+    headless::run_ticks(&mut app, ticks + 2);
+    assert_eq!(paddle_height(&mut app), original);
+    let mut effects = app.world.query::<&ActiveEffect>();
+    assert_eq!(effects.iter(&app.world).count(), 0);
+    assert_eq!(effects_on(&app, paddle), 0);
+}
+
+#[test]
+fn reversed_controls_expire() {
+    let mut app = headless::headless_app();
+    headless::run_ticks(&mut app, 1);
+    let paddle = player_paddle(&mut app);
+
+    collect(&mut app, PowerUpKind::ReversedControls);
+    headless::run_ticks(&mut app, 2);
+    assert!(app.world.get::<ReversedControls>(paddle).is_some());
+
+    let ticks = (PowerUpKind::ReversedControls.duration() * headless::TICK_RATE as f32) as u32;
+    headless::run_ticks(&mut app, ticks + 2);
+    assert!(app.world.get::<ReversedControls>(paddle).is_none());
+}
+
+#[test]
+fn multi_ball_adds_a_moving_ball() {
+    let mut app = headless::headless_app();
+    // wait for the launch so the extra ball inherits a velocity
+    headless::run_ticks(&mut app, 40);
+    collect(&mut app, PowerUpKind::MultiBall);
+    headless::run_ticks(&mut app, 2);
+
+    let mut extra = app
+        .world
+        .query_filtered::<&Velocity, (With<Ball>, With<ExtraBall>)>();
+    let velocities: Vec<_> = extra.iter(&app.world).collect();
+    assert_eq!(velocities.len(), 1);
+    assert_ne!(velocities[0].linvel, Vec2::ZERO);
+}
+
+fn ball_speed(app: &mut App) -> f32 {
+    let ball = first_ball(app);
+    app.world.get::<Velocity>(ball).unwrap().linvel.length()
+}
+
+fn effect_count(app: &mut App) -> usize {
+    let mut effects = app.world.query::<&ActiveEffect>();
+    effects.iter(&app.world).count()
+}
+
+#[test]
+fn slow_ball_keeps_the_speed_gained_while_slowed() {
+    // the ball bounces straight between two still paddles, speeding up on every hit
+    let mut app = headless::headless_app();
+    app.insert_resource(MatchMode::TwoPlayer);
+    headless::run_ticks(&mut app, 40);
+    let original = ball_speed(&mut app);
+
+    collect(&mut app, PowerUpKind::SlowBall);
+    headless::run_ticks(&mut app, 2);
+    assert!((ball_speed(&mut app) - original / 2.0).abs() < 0.5);
+
+    let ticks = (PowerUpKind::SlowBall.duration() * headless::TICK_RATE as f32) as u32;
+    headless::run_ticks(&mut app, ticks - 2);
+    let slowed = ball_speed(&mut app);
+    assert!(slowed > original / 2.0, "sped up by paddle hits");
+    headless::run_ticks(&mut app, 4);
+    assert_eq!(effect_count(&mut app), 0);
+    assert!((ball_speed(&mut app) - slowed * 2.0).abs() < 0.5);
+    assert!(ball_speed(&mut app) > original);
+}
+
+#[test]
+fn slow_ball_ends_when_the_ball_is_reset() {
+    let mut app = headless::headless_app();
+    headless::run_ticks(&mut app, 40);
+    collect(&mut app, PowerUpKind::SlowBall);
+    headless::run_ticks(&mut app, 2);
+    assert_eq!(effect_count(&mut app), 1);
+
+    let ball = first_ball(&mut app);
+    app.world
+        .resource_mut::<Events<GoalEvent>>()
+        .send(GoalEvent {
+            ball,
+            team: Team::Player,
+        });
+    headless::run_ticks(&mut app, 1);
+    assert_eq!(effect_count(&mut app), 0);
+    assert_eq!(effects_on(&app, ball), 0);
+
+    // relaunched at its initial speed, and left there
+    headless::run_ticks(&mut app, 40);
+    let initial = app.world.get::<Ball>(ball).unwrap().initial_speed;
+    assert_eq!(ball_speed(&mut app), initial);
+}
+
+#[test]
+fn faster_paddle_expires() {
+    let mut app = headless::headless_app();
+    headless::run_ticks(&mut app, 1);
+    let paddle = player_paddle(&mut app);
+    let original = app.world.get::<Paddle>(paddle).unwrap().speed;
+
+    collect(&mut app, PowerUpKind::FasterPaddle);
+    headless::run_ticks(&mut app, 2);
+    assert!(app.world.get::<Paddle>(paddle).unwrap().speed > original);
+
+    let ticks = (PowerUpKind::FasterPaddle.duration() * headless::TICK_RATE as f32) as u32;
+    headless::run_ticks(&mut app, ticks + 2);
+    assert_eq!(app.world.get::<Paddle>(paddle).unwrap().speed, original);
+}
+
+#[test]
+fn config_reloads_keep_paddle_effects_running() {
+    let mut app = headless::headless_app();
+    headless::run_ticks(&mut app, 1);
+    let paddle = player_paddle(&mut app);
+
+    collect(&mut app, PowerUpKind::BiggerPaddle);
+    headless::run_ticks(&mut app, 2);
+    collect(&mut app, PowerUpKind::FasterPaddle);
+    headless::run_ticks(&mut app, 2);
+
+    let mut config = app.world.resource_mut::<PongConfig>();
+    config.paddle.size.y = 200.0;
+    config.paddle.speed = 600.0;
+    headless::run_ticks(&mut app, 2);
+    assert_eq!(paddle_height(&mut app), 300.0);
+    assert_eq!(app.world.get::<Paddle>(paddle).unwrap().speed, 900.0);
+
+    // and expire to the reloaded values
+    let ticks = (PowerUpKind::FasterPaddle.duration() * headless::TICK_RATE as f32) as u32;
+    headless::run_ticks(&mut app, ticks + 2);
+    assert_eq!(paddle_height(&mut app), 200.0);
+    assert_eq!(app.world.get::<Paddle>(paddle).unwrap().speed, 600.0);
+}
@@ -0,0 +1,213 @@
+use bevy::{
+    ecs::event::Events,
+    input::{keyboard::KeyboardInput, ElementState},
+    prelude::*,
+};
+use bevy_pong::{
+    ai::AiDifficulty,
+    game_entities::*,
+    headless,
+    replay::{Replay, ReplayError, ReplayPlayer, ReplayRecorder, MAX_REPLAY_TICKS},
+    rng::GameRng,
+};
+use bevy_rapier2d::prelude::*;
+use iyes_loopless::prelude::*;
+
+const TICKS: u32 = 600;
+
+/// Presses and releases `key` the way a keyboard does, so systems see it as just pressed.
+fn tap(app: &mut App, key: KeyCode) {
+    for state in [ElementState::Pressed, ElementState::Released] {
+        app.world
+            .resource_mut::<Events<KeyboardInput>>()
+            .send(KeyboardInput {
+                scan_code: 0,
+                key_code: Some(key),
+                state,
+            });
+        app.update();
+    }
+}
+
+fn state(app: &App) -> GameState {
+    app.world.resource::<CurrentState<GameState>>().0
+}
+
+/// Positions and velocities of every ball and paddle, in spawn order.
+fn snapshot(app: &mut App) -> Vec<(Vec3, Vec2)> {
+    let mut query = app
+        .world
+        .query_filtered::<(Entity, &Transform, &Velocity), Or<(With<Ball>, With<Paddle>)>>();
+    let mut state: Vec<_> = query
+        .iter(&app.world)
+        .map(|(entity, transform, velocity)| (entity, transform.translation, velocity.linvel))
+        .collect();
+    state.sort_by_key(|x| x.0);
+    state.into_iter().map(|(_, t, v)| (t, v)).collect()
+}
+
+fn record_match() -> (Replay, Vec<(Vec3, Vec2)>) {
+    let mut app = headless::headless_app();
+    app.insert_resource(GameRng::new(42))
+        .insert_resource(MatchMode::TwoPlayer)
+        .insert_resource(ReplayRecorder::default());
+    for tick in 0..TICKS {
+        let mut keys = app.world.resource_mut::<Input<KeyCode>>();
+        keys.release(KeyCode::W);
+        keys.release(KeyCode::Down);
+        match (tick / 40) % 3 {
+            0 => keys.press(KeyCode::W),
+            1 => keys.press(KeyCode::Down),
+            _ => (),
+        }
+        app.update();
+    }
+    let replay = app
+        .world
+        .resource::<ReplayRecorder>()
+        .replay
+        .clone()
+        .unwrap();
+    (replay, snapshot(&mut app))
+}
+
+#[test]
+fn playback_reproduces_the_recorded_match() {
+    let (replay, recorded) = record_match();
+    assert_eq!(replay.header.seed, 42);
+    assert_eq!(replay.header.match_mode, MatchMode::TwoPlayer);
+    assert_eq!(replay.inputs.len(), TICKS as usize);
+    assert!(replay.inputs.iter().any(|x| x[0] > 0.0));
+    assert!(replay.inputs.iter().any(|x| x[1] < 0.0));
+
+    let mut app = headless::headless_app();
+    app.insert_resource(ReplayPlayer::new(
+        Replay::from_bytes(&replay.to_bytes().unwrap()).unwrap(),
+    ));
+    headless::run_ticks(&mut app, TICKS);
+    assert!(app.world.resource::<ReplayPlayer>().finished());
+    assert_eq!(snapshot(&mut app), recorded);
+}
+
+#[test]
+fn replays_round_trip_and_compress_held_inputs() {
+    let (replay, _) = record_match();
+    let bytes = replay.to_bytes().unwrap();
+    assert_eq!(Replay::from_bytes(&bytes).unwrap(), replay);
+    // inputs change every 40 ticks, so runs take far less than a value per tick
+    assert!(bytes.len() < TICKS as usize * 8 / 4);
+}
+
+#[test]
+fn truncated_replays_are_rejected() {
+    let (replay, _) = record_match();
+    let bytes = replay.to_bytes().unwrap();
+    assert!(matches!(
+        Replay::from_bytes(&bytes[..bytes.len() - 1]),
+        Err(ReplayError::Corrupt(_))
+    ));
+    assert!(matches!(
+        Replay::from_bytes(b"not a replay"),
+        Err(ReplayError::Corrupt(_))
+    ));
+}
+
+#[test]
+fn replays_longer_than_the_limit_are_rejected() {
+    let (replay, _) = record_match();
+    let mut bytes = replay.to_bytes().unwrap();
+    // the last run, stretched past the limit, instead of allocating it
+    let runs = bytes.len() - 12;
+    bytes[runs..runs + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+    assert!(matches!(
+        Replay::from_bytes(&bytes),
+        Err(ReplayError::Corrupt(_))
+    ));
+
+    let mut replay = Replay::new(replay.header);
+    replay.inputs = vec![[0.0, 1.0]; MAX_REPLAY_TICKS];
+    let bytes = replay.to_bytes().unwrap();
+    assert_eq!(Replay::from_bytes(&bytes).unwrap(), replay);
+}
+
+#[test]
+fn rematches_against_the_ai_play_back_like_the_first_match() {
+    let mut app = headless::headless_app();
+    app.insert_resource(ReplayRecorder::default())
+        .insert_resource(WinCondition {
+            target_score: 3,
+            win_by: 1,
+        });
+    // play out a first match, leaving the clock and the AI's timing far from a fresh start
+    headless::run_ticks(&mut app, 1);
+    while state(&app) == GameState::Playing {
+        app.world
+            .resource_mut::<Input<KeyCode>>()
+            .press(KeyCode::Up);
+        app.update();
+    }
+    // long enough for the whole recording
+    app.insert_resource(WinCondition::default());
+    tap(&mut app, KeyCode::Space);
+    assert_eq!(state(&app), GameState::Playing);
+
+    // the space bar press was the first tick of the rematch
+    headless::run_ticks(&mut app, TICKS - 1);
+    let replay = app
+        .world
+        .resource::<ReplayRecorder>()
+        .replay
+        .clone()
+        .unwrap();
+    assert_eq!(replay.header.match_mode, MatchMode::VsAI);
+    let recorded = snapshot(&mut app);
+
+    let mut app = headless::headless_app();
+    let ticks = replay.inputs.len() as u32;
+    app.insert_resource(ReplayPlayer::new(replay));
+    headless::run_ticks(&mut app, ticks);
+    assert_eq!(snapshot(&mut app), recorded);
+}
+
+#[test]
+fn pauses_against_the_ai_leave_no_trace_in_the_replay() {
+    // player one steers, so the ball comes back at angles the AI has to follow
+    let steer = |app: &mut App, ticks: u32| {
+        for tick in 0..ticks {
+            let mut keys = app.world.resource_mut::<Input<KeyCode>>();
+            keys.release(KeyCode::W);
+            keys.release(KeyCode::S);
+            match (tick / 20) % 3 {
+                0 => keys.press(KeyCode::W),
+                1 => keys.press(KeyCode::S),
+                _ => (),
+            }
+            app.update();
+        }
+    };
+    let mut app = headless::headless_app();
+    app.insert_resource(ReplayRecorder::default());
+    steer(&mut app, 200);
+    tap(&mut app, KeyCode::Escape);
+    assert_eq!(state(&app), GameState::Paused);
+    // a pause of a length the AI's reaction delay doesn't divide
+    headless::run_ticks(&mut app, 37);
+    tap(&mut app, KeyCode::Escape);
+    assert_eq!(state(&app), GameState::Playing);
+    steer(&mut app, TICKS);
+
+    let replay = app
+        .world
+        .resource::<ReplayRecorder>()
+        .replay
+        .clone()
+        .unwrap();
+    assert_eq!(replay.header.ai_difficulty, AiDifficulty::Normal);
+    let recorded = snapshot(&mut app);
+
+    let mut app = headless::headless_app();
+    let ticks = replay.inputs.len() as u32;
+    app.insert_resource(ReplayPlayer::new(replay));
+    headless::run_ticks(&mut app, ticks);
+    assert_eq!(snapshot(&mut app), recorded);
+}
@@ -0,0 +1,186 @@
+use bevy::{ecs::event::Events, prelude::*};
+use bevy_pong::{
+    game_entities::*,
+    headless,
+    input::PlayerId,
+    server::{
+        ClientMessage, ClientRole, GameServer, MatchSnapshot, ServerConnection, ServerMessage,
+        REMATCH_DELAY,
+    },
+};
+use iyes_loopless::prelude::*;
+use std::{net::SocketAddr, time::Duration};
+
+fn server_app() -> (App, SocketAddr) {
+    let server = GameServer::bind("127.0.0.1:0").unwrap();
+    let addr = server.local_addr().unwrap();
+    let mut app = headless::headless_app();
+    app.insert_resource(server);
+    (app, addr)
+}
+
+fn connect(
+    app: &mut App,
+    addr: SocketAddr,
+    role: ClientRole,
+) -> (ServerConnection, Option<PlayerId>) {
+    let mut connection = ServerConnection::connect(addr, role).unwrap();
+    connection
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    app.update();
+    match connection.receive().unwrap() {
+        ServerMessage::Welcome { player } => (connection, player),
+        message => panic!("expected a welcome, got {:?}", message),
+    }
+}
+
+fn next_snapshot(connection: &mut ServerConnection) -> MatchSnapshot {
+    match connection.receive().unwrap() {
+        ServerMessage::Snapshot(snapshot) => snapshot,
+        message => panic!("expected a snapshot, got {:?}", message),
+    }
+}
+
+#[test]
+fn the_match_starts_once_both_players_joined() {
+    let (mut app, addr) = server_app();
+    let (mut spectator, player) = connect(&mut app, addr, ClientRole::Spectator);
+    assert_eq!(player, None);
+    let (_one, player) = connect(&mut app, addr, ClientRole::Player);
+    assert_eq!(player, Some(PlayerId::One));
+    assert_eq!(next_snapshot(&mut spectator).state, GameState::MainMenu);
+
+    let (_two, player) = connect(&mut app, addr, ClientRole::Player);
+    assert_eq!(player, Some(PlayerId::Two));
+    // a third player can only watch
+    let (_late, player) = connect(&mut app, addr, ClientRole::Player);
+    assert_eq!(player, None);
+    app.update();
+
+    let server = app.world.resource::<GameServer>();
+    assert_eq!(server.player_count(), 2);
+    assert_eq!(server.spectator_count(), 2);
+    let snapshot = (0..4)
+        .map(|_| next_snapshot(&mut spectator))
+        .last()
+        .unwrap();
+    assert_eq!(snapshot.state, GameState::Playing);
+    assert_eq!(snapshot.balls.len(), 1);
+    assert_eq!(snapshot.score, [0, 0]);
+}
+
+#[test]
+fn spectators_see_the_players_move_but_cannot_steer() {
+    let (mut app, addr) = server_app();
+    let (mut one, _) = connect(&mut app, addr, ClientRole::Player);
+    let (_two, _) = connect(&mut app, addr, ClientRole::Player);
+    let (mut spectator, _) = connect(&mut app, addr, ClientRole::Spectator);
+
+    one.send(&ClientMessage::Input(1.0)).unwrap();
+    spectator.send(&ClientMessage::Input(-1.0)).unwrap();
+    headless::run_ticks(&mut app, 30);
+
+    let mut snapshot = next_snapshot(&mut spectator);
+    while snapshot.tick < app.world.resource::<GameClock>().tick {
+        snapshot = next_snapshot(&mut spectator);
+    }
+    let height = |player| {
+        snapshot
+            .paddles
+            .iter()
+            .find(|x| x.0 == player)
+            .unwrap()
+            .1
+            .translation
+            .y
+    };
+    assert!(height(PlayerId::One) > 0.0);
+    assert_eq!(height(PlayerId::Two), 0.0);
+}
+
+#[test]
+fn a_leaving_player_frees_their_paddle() {
+    let (mut app, addr) = server_app();
+    let (one, _) = connect(&mut app, addr, ClientRole::Player);
+    let (_two, _) = connect(&mut app, addr, ClientRole::Player);
+    drop(one);
+    headless::run_ticks(&mut app, 2);
+    assert_eq!(app.world.resource::<GameServer>().player_count(), 1);
+
+    let (_three, player) = connect(&mut app, addr, ClientRole::Player);
+    assert_eq!(player, Some(PlayerId::One));
+}
+
+#[test]
+fn players_sending_non_finite_inputs_are_dropped() {
+    let (mut app, addr) = server_app();
+    let (mut one, _) = connect(&mut app, addr, ClientRole::Player);
+    let (mut two, _) = connect(&mut app, addr, ClientRole::Player);
+    one.send(&ClientMessage::Input(f32::NAN)).unwrap();
+    two.send(&ClientMessage::Input(f32::NEG_INFINITY)).unwrap();
+    headless::run_ticks(&mut app, 30);
+    assert_eq!(app.world.resource::<GameServer>().player_count(), 0);
+
+    let mut paddles = app.world.query::<&Transform>();
+    assert!(paddles.iter(&app.world).all(|x| x.translation.is_finite()));
+}
+
+fn state(app: &App) -> GameState {
+    app.world.resource::<CurrentState<GameState>>().0
+}
+
+fn win_match(app: &mut App) {
+    app.insert_resource(WinCondition {
+        target_score: 1,
+        win_by: 1,
+    });
+    let mut balls = app.world.query_filtered::<Entity, With<Ball>>();
+    let ball = balls.iter(&app.world).next().unwrap();
+    app.world
+        .resource_mut::<Events<GoalEvent>>()
+        .send(GoalEvent {
+            ball,
+            team: Team::Player,
+        });
+    headless::run_ticks(app, 2);
+    assert_eq!(state(app), GameState::GameOver);
+}
+
+const REMATCH_TICKS: u32 = (REMATCH_DELAY.as_secs() as u32 + 1) * headless::TICK_RATE as u32;
+
+#[test]
+fn a_rematch_starts_on_its_own_after_a_win() {
+    let (mut app, addr) = server_app();
+    let (_one, _) = connect(&mut app, addr, ClientRole::Player);
+    let (_two, _) = connect(&mut app, addr, ClientRole::Player);
+    headless::run_ticks(&mut app, 30);
+    win_match(&mut app);
+
+    headless::run_ticks(&mut app, REMATCH_TICKS);
+    assert_eq!(state(&app), GameState::Playing);
+    let mut scores = app.world.query::<&MatchScore>();
+    let score = scores.iter(&app.world).next().unwrap();
+    assert!(score.score.values().all(|x| *x == 0));
+
+    // and so does the one after it
+    win_match(&mut app);
+    headless::run_ticks(&mut app, REMATCH_TICKS);
+    assert_eq!(state(&app), GameState::Playing);
+}
+
+#[test]
+fn players_leaving_after_a_win_are_waited_for() {
+    let (mut app, addr) = server_app();
+    let (one, _) = connect(&mut app, addr, ClientRole::Player);
+    let (_two, _) = connect(&mut app, addr, ClientRole::Player);
+    headless::run_ticks(&mut app, 30);
+    win_match(&mut app);
+    drop(one);
+
+    headless::run_ticks(&mut app, REMATCH_TICKS);
+    assert_eq!(state(&app), GameState::MainMenu);
+    let (_three, _) = connect(&mut app, addr, ClientRole::Player);
+    headless::run_ticks(&mut app, 2);
+    assert_eq!(state(&app), GameState::Playing);
+}
@@ -0,0 +1,80 @@
+use bevy::{ecs::schedule::IntoSystemDescriptor, prelude::*};
+use bevy_pong::{game_entities::TickMode, tick::*};
+use std::time::Duration;
+
+fn run_system<Params>(world: &mut World, system: impl IntoSystemDescriptor<Params>) {
+    SystemStage::single_threaded()
+        .with_system(system)
+        .run(world);
+}
+
+#[test]
+fn transforms_are_drawn_between_ticks_and_restored_for_the_next() {
+    let mut world = World::new();
+    let entity = world
+        .spawn()
+        .insert(Transform::from_xyz(0.0, 0.0, 0.0))
+        .insert(TickInterpolation::default())
+        .id();
+    run_system(&mut world, record_physics_transforms);
+    world.get_mut::<Transform>(entity).unwrap().translation.x = 100.0;
+    run_system(&mut world, record_physics_transforms);
+
+    world.insert_resource(TickOverstep(0.25));
+    run_system(&mut world, interpolate_transforms);
+    assert_eq!(world.get::<Transform>(entity).unwrap().translation.x, 25.0);
+
+    run_system(&mut world, restore_physics_transforms);
+    assert_eq!(world.get::<Transform>(entity).unwrap().translation.x, 100.0);
+}
+
+#[test]
+fn teleported_transforms_are_not_interpolated() {
+    let mut world = World::new();
+    let entity = world
+        .spawn()
+        .insert(Transform::from_xyz(0.0, 0.0, 0.0))
+        .insert(TickInterpolation::default())
+        .id();
+    run_system(&mut world, record_physics_transforms);
+    world.get_mut::<Transform>(entity).unwrap().translation.x = 100.0;
+    run_system(&mut world, record_physics_transforms);
+
+    // moved outside the tick, e.g. by the arena layout
+    world.get_mut::<Transform>(entity).unwrap().translation.x = 500.0;
+    world.insert_resource(TickOverstep(0.5));
+    run_system(&mut world, interpolate_transforms);
+    assert_eq!(world.get::<Transform>(entity).unwrap().translation.x, 500.0);
+
+    run_system(&mut world, restore_physics_transforms);
+    assert_eq!(world.get::<Transform>(entity).unwrap().translation.x, 500.0);
+}
+
+#[derive(Default)]
+struct Ticks(u32);
+
+fn count_ticks(mut ticks: ResMut<Ticks>) {
+    ticks.0 += 1;
+}
+
+#[test]
+fn stalls_only_catch_up_a_few_ticks() {
+    let mut world = World::new();
+    world.init_resource::<Ticks>();
+    let step = Duration::from_millis(20);
+    let mut stage = FixedTickStage::new(TickMode::RealTime, step);
+    stage.stage_mut(TickStage::Gameplay).add_system(count_ticks);
+
+    let mut time = Time::default();
+    time.update();
+    std::thread::sleep(step * MAX_CATCH_UP_TICKS * 3);
+    time.update();
+    world.insert_resource(time);
+    stage.run(&mut world);
+    assert_eq!(world.resource::<Ticks>().0, MAX_CATCH_UP_TICKS);
+
+    // nothing of the stall is left over for the next frame
+    world.resource_mut::<Time>().update();
+    stage.run(&mut world);
+    assert_eq!(world.resource::<Ticks>().0, MAX_CATCH_UP_TICKS);
+}
@@ -0,0 +1,56 @@
+use bevy::prelude::*;
+use bevy_pong::trajectory::*;
+use bevy_rapier2d::prelude::Velocity;
+
+fn bounds() -> ArenaBounds {
+    ArenaBounds {
+        min: Vec2::new(-500.0, -100.0),
+        max: Vec2::new(500.0, 100.0),
+    }
+}
+
+fn velocity(x: f32, y: f32) -> Velocity {
+    Velocity::linear(Vec2::new(x, y))
+}
+
+#[test]
+fn straight_shot() {
+    let intercept =
+        predict_intercept(Vec2::ZERO, &velocity(100.0, 0.0), 10.0, &bounds(), 400.0).unwrap();
+    assert_eq!(intercept.point, Vec2::new(400.0, 0.0));
+    assert_eq!(intercept.time, 4.0);
+    assert_eq!(intercept.bounces, 0);
+}
+
+#[test]
+fn single_bounce_off_top_wall() {
+    // radius 10 puts the reflection line at y = 90
+    let intercept =
+        predict_intercept(Vec2::ZERO, &velocity(100.0, 100.0), 10.0, &bounds(), 120.0).unwrap();
+    assert_eq!(intercept.bounces, 1);
+    assert!((intercept.point.y - 60.0).abs() < 1e-3);
+    assert_eq!(intercept.velocity, Vec2::new(100.0, -100.0));
+}
+
+#[test]
+fn two_bounces_keep_direction() {
+    let intercept = predict_intercept(
+        Vec2::ZERO,
+        &velocity(-100.0, -100.0),
+        10.0,
+        &bounds(),
+        -300.0,
+    )
+    .unwrap();
+    assert_eq!(intercept.bounces, 2);
+    assert!((intercept.point.y - 60.0).abs() < 1e-3);
+    assert_eq!(intercept.velocity, Vec2::new(-100.0, -100.0));
+}
+
+#[test]
+fn ball_moving_away_never_intercepts() {
+    assert!(
+        predict_intercept(Vec2::ZERO, &velocity(-100.0, 0.0), 10.0, &bounds(), 400.0).is_none()
+    );
+    assert!(predict_intercept(Vec2::ZERO, &velocity(0.0, 100.0), 10.0, &bounds(), 400.0).is_none());
+}